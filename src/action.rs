@@ -0,0 +1,231 @@
+use std::collections::{HashMap, HashSet};
+
+use uuid::Uuid;
+
+use crate::{backend::Change, data::{self, Task, TaskStatus}, ui::prompt::{Choice, Prompt}};
+
+/// Something a tab wants the app to do, since tabs can't change tasks themselves.
+#[derive(Debug, Clone)]
+pub enum Action {
+    /// Write changes to Taskwarrior, all at once
+    Apply(Vec<Change>),
+    /// Ask the user first, each choice has its own actions
+    Prompt(Prompt),
+    /// Revert the last batch of changes made through tasksmith
+    Undo,
+    Redo,
+    /// Write everything staged in dry run mode
+    Commit,
+    /// Throw away everything staged in dry run mode
+    Discard,
+}
+
+/// Completing a task, asking first.
+///
+/// With `complete_parents` parents whose last pending subtask this was are offered to be
+/// completed too, and completing a task that still has pending subtasks warns about them.
+pub fn complete(tasks: &HashMap<Uuid, Task>, task: &Task, complete_parents: bool) -> Vec<Action> {
+    if task.is_resolved() {
+        return vec![];
+    }
+    let change = Change::complete(task);
+    if !complete_parents {
+        return vec![Action::Prompt(Prompt::confirm(
+            "Complete",
+            format!("Complete \"{}\"?", task.description),
+            vec![Action::Apply(vec![change])],
+        ))];
+    }
+
+    let parents = completable_parents(tasks, task);
+    let pending = pending_descendants(tasks, task);
+    if pending.is_empty() {
+        return vec![Action::Prompt(Prompt::confirm(
+            "Complete",
+            format!("Complete \"{}\"?", task.description),
+            vec![with_parents(vec![change], &parents)],
+        ))];
+    }
+
+    let mut complete_all = vec![change.clone()];
+    complete_all.extend(pending.iter().map(|t| Change::complete(t)));
+    let mut detach: Vec<Change> = data::children(tasks, &task.uuid)
+        .filter(|t| !t.is_resolved())
+        .map(|t| Change::modify(t, |t| t.sub_of = None))
+        .collect();
+    detach.push(change);
+
+    vec![Action::Prompt(Prompt::new(
+        "Pending subtasks",
+        format!("\"{}\" still has {} pending subtasks.", task.description, pending.len()),
+        vec![
+            Choice::new('c', "Complete them", vec![with_parents(complete_all, &parents)]),
+            Choice::new('d', "Detach them", vec![with_parents(detach, &parents)]),
+            Choice::new('n', "Cancel", vec![]),
+        ],
+    ))]
+}
+
+/// Deleting a task, asking first. Subtasks can be deleted along with it, or moved up to its parent.
+pub fn delete(tasks: &HashMap<Uuid, Task>, task: &Task) -> Vec<Action> {
+    if task.status == TaskStatus::Deleted {
+        return vec![];
+    }
+    let change = Change::modify(task, |t| t.status = TaskStatus::Deleted);
+    let children: Vec<&Task> = data::children(tasks, &task.uuid)
+        .filter(|t| t.status != TaskStatus::Deleted)
+        .collect();
+    if children.is_empty() {
+        return vec![Action::Prompt(Prompt::confirm(
+            "Delete",
+            format!("Delete \"{}\"?", task.description),
+            vec![Action::Apply(vec![change])],
+        ))];
+    }
+
+    let descendants: Vec<&Task> = descendants(tasks, task).into_iter()
+        .filter(|t| t.status != TaskStatus::Deleted)
+        .collect();
+    let mut subtree = vec![change.clone()];
+    subtree.extend(descendants.iter().map(|t| Change::modify(t, |t| t.status = TaskStatus::Deleted)));
+    let mut reparent: Vec<Change> = children.iter()
+        .map(|t| Change::modify(t, |t| t.sub_of = task.sub_of))
+        .collect();
+    reparent.push(change);
+
+    vec![Action::Prompt(Prompt::new(
+        "Delete subtasks",
+        format!("\"{}\" has {} subtasks.", task.description, descendants.len()),
+        vec![
+            Choice::new('a', "Delete all of them", vec![Action::Apply(subtree)]),
+            Choice::new('r', "Move them up", vec![Action::Apply(reparent)]),
+            Choice::new('n', "Cancel", vec![]),
+        ],
+    ))]
+}
+
+/// Asks to complete each parent in turn, everything is applied at once at the end.
+fn with_parents(mut changes: Vec<Change>, parents: &[&Task]) -> Action {
+    let Some((parent, rest)) = parents.split_first() else {
+        return Action::Apply(changes);
+    };
+    let without = Action::Apply(changes.clone());
+    changes.push(Change::complete(parent));
+    Action::Prompt(Prompt::new(
+        "Complete parent",
+        format!("Every subtask of \"{}\" is done, complete it too?", parent.description),
+        vec![
+            Choice::new('y', "Yes", vec![with_parents(changes, rest)]),
+            Choice::new('n', "No", vec![without]),
+        ],
+    ))
+}
+
+/// Parents, going upwards, that would have nothing pending left once `task` is done
+fn completable_parents<'a>(tasks: &'a HashMap<Uuid, Task>, task: &'a Task) -> Vec<&'a Task> {
+    let mut done: HashSet<Uuid> = HashSet::from([task.uuid]);
+    let mut parents = vec![];
+    let mut current = task;
+    while let Some(parent) = current.sub_of.and_then(|p| tasks.get(&p)) {
+        if parent.is_resolved() || data::children(tasks, &parent.uuid).any(|c| !c.is_resolved() && !done.contains(&c.uuid)) {
+            break;
+        }
+        done.insert(parent.uuid);
+        parents.push(parent);
+        current = parent;
+    }
+    parents
+}
+
+fn pending_descendants<'a>(tasks: &'a HashMap<Uuid, Task>, task: &'a Task) -> Vec<&'a Task> {
+    descendants(tasks, task).into_iter().filter(|t| !t.is_resolved()).collect()
+}
+
+/// Every task below a task through `sub_of`
+fn descendants<'a>(tasks: &'a HashMap<Uuid, Task>, task: &'a Task) -> Vec<&'a Task> {
+    let mut descendants = vec![];
+    let mut seen: HashSet<Uuid> = HashSet::from([task.uuid]);
+    let mut stack: Vec<&Task> = data::children(tasks, &task.uuid).collect();
+    while let Some(child) = stack.pop() {
+        if !seen.insert(child.uuid) {
+            continue;
+        }
+        descendants.push(child);
+        stack.extend(data::children(tasks, &child.uuid));
+    }
+    descendants
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parents_up_the_chain() {
+        let root = Task::new("Root".to_string());
+        let mut parent = Task::new("Parent".to_string());
+        parent.sub_of = Some(root.uuid);
+        let mut child = Task::new("Child".to_string());
+        child.sub_of = Some(parent.uuid);
+        let mut done = Task::new("Done".to_string());
+        done.sub_of = Some(parent.uuid);
+        done.complete();
+        let mut other = Task::new("Other".to_string());
+        other.sub_of = Some(root.uuid);
+
+        let mut tasks: HashMap<Uuid, Task> = vec![root.clone(), parent.clone(), child.clone(), done, other.clone()]
+            .into_iter()
+            .map(|t| (t.uuid, t))
+            .collect();
+
+        let parents: Vec<Uuid> = completable_parents(&tasks, &child).iter().map(|t| t.uuid).collect();
+        assert_eq!(parents, vec![parent.uuid]);
+
+        tasks.get_mut(&other.uuid).unwrap().complete();
+        let parents: Vec<Uuid> = completable_parents(&tasks, &child).iter().map(|t| t.uuid).collect();
+        assert_eq!(parents, vec![parent.uuid, root.uuid]);
+
+        let pending: Vec<Uuid> = pending_descendants(&tasks, &root).iter().map(|t| t.uuid).collect();
+        assert_eq!(pending.len(), 2);
+        assert!(pending.contains(&parent.uuid) && pending.contains(&child.uuid));
+    }
+
+    fn choice(actions: &[Action], key: char) -> Vec<Change> {
+        let Some(Action::Prompt(prompt)) = actions.first() else {
+            panic!("expected a prompt");
+        };
+        match &prompt.choices.iter().find(|c| c.key == key).unwrap().actions[..] {
+            [Action::Apply(changes)] => changes.clone(),
+            _ => panic!("expected changes"),
+        }
+    }
+
+    #[test]
+    fn delete_subtree() {
+        let root = Task::new("Root".to_string());
+        let mut parent = Task::new("Parent".to_string());
+        parent.sub_of = Some(root.uuid);
+        let mut child = Task::new("Child".to_string());
+        child.sub_of = Some(parent.uuid);
+        let mut grandchild = Task::new("Grandchild".to_string());
+        grandchild.sub_of = Some(child.uuid);
+        let tasks: HashMap<Uuid, Task> = vec![root.clone(), parent.clone(), child.clone(), grandchild.clone()]
+            .into_iter()
+            .map(|t| (t.uuid, t))
+            .collect();
+
+        let all = choice(&delete(&tasks, &parent), 'a');
+        assert_eq!(all.len(), 3);
+        assert!(all.iter().all(|c| c.after.status == TaskStatus::Deleted));
+
+        let up = choice(&delete(&tasks, &parent), 'r');
+        assert_eq!(up.len(), 2);
+        assert_eq!(up[0].after.uuid, child.uuid);
+        assert_eq!(up[0].after.sub_of, Some(root.uuid));
+        assert_eq!(up[1].after.status, TaskStatus::Deleted);
+
+        let leaf = choice(&delete(&tasks, &grandchild), 'y');
+        assert_eq!(leaf.len(), 1);
+    }
+}
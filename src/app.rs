@@ -1,36 +1,81 @@
 use std::{collections::HashMap, sync::atomic::{AtomicBool, Ordering}};
 
-use color_eyre::eyre::Result;
-use crossterm::event::{KeyCode, KeyModifiers};
-use ratatui::{layout::Rect, Frame};
+use chrono::{NaiveDateTime, Utc};
+use color_eyre::eyre::{eyre, Result};
+use crossterm::event::{KeyCode, KeyModifiers, MouseButton, MouseEventKind};
+use ratatui::{layout::{Constraint, Direction, Layout, Rect}, Frame};
 use uuid::Uuid;
 
-use crate::{data::{get_tasks, Task}, event::Event, tabs::list::List, ui::style::SharedTheme};
+use crate::{action::Action, backend::{Backend, Change, TaskwarriorBackend}, config::{Colors, Config, SharedConfig, TimewarriorConfig}, data::{get_tasks, Task}, event::Event, history::{self, History}, tabs::{calendar::Calendar, kanban::Kanban, list::List, Tab}, taskrc::{SharedColors, SharedUdas, TaskRc}, staging::Staging, timew::{self, SharedTracked, Tracked}, ui::{active::ActiveWidget, changes::ChangesWidget, prompt::{Choice, Prompt, PromptResult}, glyphs::GlyphSet, style::{SharedTheme, Theme}, tabbar::TabBar}};
 
 #[derive(Debug)]
 pub struct App {
     pub should_quit: AtomicBool,
     pub theme: SharedTheme,
-    pub list: List,
+    pub tabs: Vec<Box<dyn Tab>>,
+    pub active: usize,
     pub tasks: HashMap<Uuid, Task>,
-    last_size: Option<Rect>
+    backend: Box<dyn Backend>,
+    history: History,
+    /// Only there in dry run mode
+    staging: Option<Staging>,
+    prompt: Option<Prompt>,
+    last_size: Option<Rect>,
+    now: NaiveDateTime,
+    /// Where the tab bar was drawn, for clicking on tabs
+    tab_bar: Rect,
+    timewarrior: TimewarriorConfig,
+    /// Loaded along with the tasks, and shared with the lists
+    tracked: SharedTracked,
+    /// Only reported when tracking starts failing, not on every reload after that
+    tracking_failed: bool,
 }
 
 impl App {
 
     pub fn new() -> Result<Self> {
         let task_map = get_tasks()?;
-        let list = List::new(&task_map.clone());
-        Ok(Self {
+        let config = SharedConfig::new(Config::load()?);
+        // Taskwarrior's config is only needed for extras, so tasks can still be shown without it
+        let taskrc = TaskRc::load().unwrap_or_default();
+        let udas = SharedUdas::new(taskrc.udas());
+        let colors = (config.colors == Colors::Taskrc).then(|| SharedColors::new(taskrc.colors()));
+        let tracked = SharedTracked::default();
+        let list = List::new(&task_map.clone(), config.clone(), udas.clone(), colors.clone(), tracked.clone());
+        let agenda = List::agenda(&task_map, config.clone(), udas.clone(), colors, tracked.clone());
+        let kanban = Kanban::new(&task_map, &config.kanban, udas.clone());
+        let calendar = Calendar::new(&task_map);
+        let staging = config.dry_run.then(|| Staging::new(task_map.clone()));
+        let theme = Theme::load(&config.theme)?.with_glyphs(GlyphSet::detect(config.glyphs).glyphs());
+        let mut app = Self {
             should_quit: false.into(),
-            theme: SharedTheme::default(),
-            list,
+            theme: SharedTheme::new(theme),
+            tabs: vec![Box::new(list), Box::new(agenda), Box::new(kanban), Box::new(calendar)],
+            active: 0,
             tasks: task_map,
-            last_size: None
-        })
+            backend: Box::new(TaskwarriorBackend),
+            history: History::default(),
+            staging,
+            prompt: None,
+            last_size: None,
+            now: Utc::now().naive_utc(),
+            tab_bar: Rect::default(),
+            timewarrior: config.timewarrior.clone(),
+            tracked,
+            tracking_failed: false,
+        };
+        app.load_tracked();
+        app.refresh_tabs();
+        Ok(app)
     }
 
-    pub fn tick(&self) {}
+    /// Keeps timers of started tasks running
+    pub fn tick(&mut self) {
+        self.now = Utc::now().naive_utc();
+        for tab in &mut self.tabs {
+            tab.tick();
+        }
+    }
 
     pub fn quit(&mut self)  {
         self.should_quit.swap(true, Ordering::Relaxed);
@@ -38,24 +83,368 @@ impl App {
 
     pub fn draw(&mut self, frame: &mut Frame) -> Result<()> {
         let fsize = frame.size();
-        self.list.draw(self.theme.clone(), frame, fsize, &self.tasks)?;
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(1), Constraint::Fill(1)])
+            .split(fsize);
+        let titles: Vec<String> = self.tabs.iter().map(|t| t.title()).collect();
+        TabBar::new(&titles, self.active, self.theme.clone()).render(layout[0], frame.buffer_mut());
+        self.tab_bar = layout[0];
+
+        let mut tab_area = layout[1];
+        let active: Vec<&Task> = self.tasks.values().filter(|t| t.start.is_some() && !t.is_resolved()).collect();
+        if !active.is_empty() {
+            let layout = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Length(1), Constraint::Fill(1)])
+                .split(tab_area);
+            ActiveWidget::new(active, self.now, self.theme.clone()).render(layout[0], frame.buffer_mut());
+            tab_area = layout[1];
+        }
+        if let Some(staging) = &self.staging {
+            let changes = staging.coalesced();
+            let panel = ChangesWidget::new(&changes, self.theme.clone());
+            let layout = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Fill(1), Constraint::Max(panel.height().min(tab_area.height / 3))])
+                .split(tab_area);
+            tab_area = layout[0];
+            panel.render(layout[1], frame.buffer_mut());
+        }
+        self.tabs[self.active].draw(self.theme.clone(), frame, tab_area, &self.tasks)?;
+        if let Some(prompt) = &self.prompt {
+            prompt.render(fsize, frame.buffer_mut(), self.theme.clone());
+        }
         Ok(())
     }
 
     pub fn event(&mut self, event: Event) {
+        if let Event::Key(k) = event {
+            if k.code == KeyCode::Char('c') && KeyModifiers::CONTROL == k.modifiers {
+                self.quit();
+                return;
+            }
+        }
+        if let Some(prompt) = &mut self.prompt {
+            if let PromptResult::Closed(actions) = prompt.event(event) {
+                self.prompt = None;
+                self.run(actions);
+            }
+            return;
+        }
+        if let Event::Mouse(m) = event {
+            if m.kind == MouseEventKind::Down(MouseButton::Left) && m.row == self.tab_bar.y {
+                let titles: Vec<String> = self.tabs.iter().map(|t| t.title()).collect();
+                if let Some(tab) = TabBar::new(&titles, self.active, self.theme.clone()).tab_at(self.tab_bar, m.column) {
+                    self.select_tab(tab);
+                }
+                return;
+            }
+        }
         if let Event::Key(k) = event {
             match k.code {
-                KeyCode::Char('c') => {
-                    if KeyModifiers::CONTROL == k.modifiers {
-                        self.quit()
-                    }
+                KeyCode::Char('q') => {
+                    self.quit();
+                    return;
+                },
+                KeyCode::Char('u') => {
+                    self.confirm_undo();
+                    return;
+                },
+                KeyCode::Char('r') if KeyModifiers::CONTROL == k.modifiers => {
+                    self.confirm_redo();
+                    return;
+                },
+                KeyCode::Char('P') => {
+                    self.toggle_dry_run();
+                    return;
+                },
+                KeyCode::Char('W') => {
+                    self.confirm_commit();
+                    return;
+                },
+                KeyCode::Char('X') => {
+                    self.confirm_discard();
+                    return;
+                },
+                KeyCode::Tab => {
+                    self.select_tab((self.active + 1) % self.tabs.len());
+                    return;
+                },
+                KeyCode::BackTab => {
+                    self.select_tab((self.active + self.tabs.len() - 1) % self.tabs.len());
+                    return;
+                },
+                KeyCode::Char(c @ '1'..='9') => {
+                    self.select_tab(c as usize - '1' as usize);
+                    return;
                 },
-                KeyCode::Char('q') => self.quit(),
                 _ => {}
             }
         }
-        self.list.event(event, &self.tasks);
-        
+        let actions = self.tabs[self.active].event(event, &self.tasks);
+        self.run(actions);
+    }
+
+    fn select_tab(&mut self, index: usize) {
+        if index < self.tabs.len() {
+            self.active = index;
+        }
+    }
+
+    fn refresh_tabs(&mut self) {
+        for tab in &mut self.tabs {
+            tab.refresh(&self.tasks);
+        }
+    }
+
+    fn toggle_dry_run(&mut self) {
+        match &self.staging {
+            None => self.staging = Some(Staging::new(self.tasks.clone())),
+            Some(staging) if staging.is_empty() => self.staging = None,
+            Some(staging) => {
+                let count = staging.coalesced().len();
+                self.prompt = Some(Prompt::new(
+                    "Dry run",
+                    format!("{} tasks have staged changes, write or discard them first.", count),
+                    vec![
+                        Choice::new('w', "Write", vec![Action::Commit]),
+                        Choice::new('x', "Discard", vec![Action::Discard]),
+                        Choice::new('n', "Cancel", vec![]),
+                    ],
+                ));
+            },
+        }
+    }
+
+    fn confirm_commit(&mut self) {
+        if let Some(staging) = self.staging.as_ref().filter(|s| !s.is_empty()) {
+            let message = format!("Write changes to {} tasks?", staging.coalesced().len());
+            self.prompt = Some(Prompt::confirm("Write", message, vec![Action::Commit]));
+        }
+    }
+
+    fn confirm_discard(&mut self) {
+        if let Some(staging) = self.staging.as_ref().filter(|s| !s.is_empty()) {
+            let message = format!("Discard changes to {} tasks?", staging.coalesced().len());
+            self.prompt = Some(Prompt::confirm("Discard", message, vec![Action::Discard]));
+        }
+    }
+
+    fn confirm_undo(&mut self) {
+        if self.staging.is_some() {
+            // Staged changes haven't been written, discarding them is the undo
+            return;
+        }
+        if let Some(changes) = self.history.next_undo() {
+            let message = format!("Undo {}?", history::describe(changes));
+            self.prompt = Some(Prompt::confirm("Undo", message, vec![Action::Undo]));
+        }
+    }
+
+    fn confirm_redo(&mut self) {
+        if self.staging.is_some() {
+            return;
+        }
+        if let Some(changes) = self.history.next_redo() {
+            let message = format!("Redo {}?", history::describe(changes));
+            self.prompt = Some(Prompt::confirm("Redo", message, vec![Action::Redo]));
+        }
+    }
+
+    fn run(&mut self, actions: Vec<Action>) {
+        for action in actions {
+            match action {
+                Action::Apply(changes) => {
+                    if let Some(staging) = &mut self.staging {
+                        staging.stage(&mut self.tasks, &changes);
+                        self.refresh_tabs();
+                        continue;
+                    }
+                    let (written, result) = self.write(&changes);
+                    self.history.record(changes[..written].to_vec());
+                    self.report(result);
+                },
+                Action::Commit => {
+                    let Some(staging) = &self.staging else {
+                        continue;
+                    };
+                    let changes = staging.coalesced();
+                    let (written, result) = self.write(&changes);
+                    self.history.record(changes[..written].to_vec());
+                    // Whatever made it through is in the export now, the rest stays staged
+                    let mut staging = Staging::new(self.tasks.clone());
+                    let unwritten = &changes[written..];
+                    staging.stage(&mut self.tasks, unwritten);
+                    self.staging = Some(staging);
+                    if !unwritten.is_empty() {
+                        self.refresh_tabs();
+                    }
+                    self.report(result.map_err(|e| {
+                        let names: Vec<String> = unwritten.iter().map(|c| format!("\"{}\"", c.after.description)).collect();
+                        eyre!("{}\n\nStill staged: {}", e, names.join(", "))
+                    }));
+                },
+                Action::Discard => {
+                    if let Some(staging) = &mut self.staging {
+                        self.tasks = staging.discard();
+                        self.refresh_tabs();
+                    }
+                },
+                Action::Undo => {
+                    if let Some(changes) = self.history.undo() {
+                        let (written, result) = self.write(&changes);
+                        self.history.undone(written);
+                        self.report(result);
+                    }
+                },
+                Action::Redo => {
+                    if let Some(changes) = self.history.redo() {
+                        let (written, result) = self.write(&changes);
+                        self.history.redone(written);
+                        self.report(result);
+                    }
+                },
+                Action::Prompt(prompt) => self.prompt = Some(prompt),
+            }
+        }
+    }
+
+    fn report(&mut self, result: Result<()>) {
+        if let Err(e) = result {
+            self.prompt = Some(Prompt::error(e.to_string()));
+        }
+    }
+
+    /// Writes changes in order until one fails, and returns how many were written
+    fn write(&mut self, changes: &[Change]) -> (usize, Result<()>) {
+        let mut written = 0;
+        let mut result = Ok(());
+        for change in changes {
+            result = self.backend.apply(std::slice::from_ref(change));
+            if result.is_err() {
+                break;
+            }
+            written += 1;
+        }
+        // Even if it failed part way through, some changes may have been made
+        (written, self.reload().and(result))
+    }
+
+    fn reload(&mut self) -> Result<()> {
+        self.tasks = self.backend.export()?;
+        self.load_tracked();
+        self.refresh_tabs();
+        Ok(())
+    }
+
+    fn load_tracked(&mut self) {
+        if !self.timewarrior.enabled {
+            return;
+        }
+        match timew::load(&self.timewarrior) {
+            Ok(intervals) => {
+                *self.tracked.borrow_mut() = Tracked::new(&intervals, &self.tasks, self.now);
+                self.tracking_failed = false;
+            },
+            Err(e) => {
+                *self.tracked.borrow_mut() = Tracked::default();
+                if !self.tracking_failed && self.prompt.is_none() {
+                    self.prompt = Some(Prompt::error(format!("Couldn't read tracked time: {}", e)));
+                }
+                self.tracking_failed = true;
+            },
+        }
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::data::TaskStatus;
+
+    use super::*;
+
+    /// Keeps tasks in memory, and fails to write any change to `fail`
+    #[derive(Debug)]
+    struct FailingBackend {
+        tasks: HashMap<Uuid, Task>,
+        fail: Option<Uuid>,
+    }
+
+    impl Backend for FailingBackend {
+
+        fn export(&mut self) -> Result<HashMap<Uuid, Task>> {
+            Ok(self.tasks.clone())
+        }
+
+        fn apply(&mut self, changes: &[Change]) -> Result<()> {
+            for change in changes {
+                if Some(change.after.uuid) == self.fail {
+                    return Err(eyre!("`task` failed"));
+                }
+                self.tasks.insert(change.after.uuid, change.after.clone());
+            }
+            Ok(())
+        }
+
+    }
+
+    fn app(tasks: &[Task]) -> App {
+        let tasks: HashMap<Uuid, Task> = tasks.iter().map(|t| (t.uuid, t.clone())).collect();
+        App {
+            should_quit: false.into(),
+            theme: SharedTheme::default(),
+            tabs: vec![],
+            active: 0,
+            tasks: tasks.clone(),
+            backend: Box::new(FailingBackend { tasks, fail: None }),
+            history: History::default(),
+            staging: None,
+            prompt: None,
+            last_size: None,
+            now: Utc::now().naive_utc(),
+            tab_bar: Rect::default(),
+            timewarrior: TimewarriorConfig::default(),
+            tracked: SharedTracked::default(),
+            tracking_failed: false,
+        }
     }
 
+    fn fail_on(app: &mut App, task: Option<&Task>) {
+        let tasks = app.tasks.clone();
+        app.backend = Box::new(FailingBackend { tasks, fail: task.map(|t| t.uuid) });
+    }
+
+    #[test]
+    fn undo_fails_part_way() {
+        let first = Task::new("First".to_string());
+        let second = Task::new("Second".to_string());
+        let mut app = app(&[first.clone(), second.clone()]);
+        app.run(vec![Action::Apply(vec![Change::complete(&first), Change::complete(&second)])]);
+        assert!(app.tasks.values().all(|t| t.status == TaskStatus::Completed));
+
+        // Undo goes backwards, so "Second" is reopened and then "First" fails
+        fail_on(&mut app, Some(&first));
+        app.run(vec![Action::Undo]);
+        assert!(app.prompt.take().is_some());
+        assert_eq!(app.tasks[&second.uuid].status, TaskStatus::Pending);
+        assert_eq!(app.tasks[&first.uuid].status, TaskStatus::Completed);
+        assert_eq!(history::describe(app.history.next_undo().unwrap()), "completing \"First\"");
+        assert_eq!(history::describe(app.history.next_redo().unwrap()), "completing \"Second\"");
+
+        // Redoing "Second" fails, so it stays to be redone
+        fail_on(&mut app, Some(&second));
+        app.run(vec![Action::Redo]);
+        assert!(app.prompt.take().is_some());
+        assert_eq!(app.tasks[&second.uuid].status, TaskStatus::Pending);
+        assert_eq!(history::describe(app.history.next_redo().unwrap()), "completing \"Second\"");
+
+        fail_on(&mut app, None);
+        app.run(vec![Action::Undo]);
+        assert!(app.prompt.is_none());
+        assert!(app.tasks.values().all(|t| t.status == TaskStatus::Pending));
+        assert!(app.history.next_undo().is_none());
+    }
 }
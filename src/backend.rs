@@ -0,0 +1,352 @@
+use std::{collections::HashMap, io::Write, process::{Command, Stdio}};
+
+use color_eyre::{eyre::eyre, Result};
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::data::{self, Task, TaskStatus};
+
+/// Where tasks are read from and written to.
+pub trait Backend: std::fmt::Debug {
+
+    fn export(&mut self) -> Result<HashMap<Uuid, Task>>;
+
+    /// Writes every change, in order.
+    fn apply(&mut self, changes: &[Change]) -> Result<()>;
+
+}
+
+/// A task before and after it was changed. Tasks that were just added have nothing before.
+#[derive(Debug, Clone)]
+pub struct Change {
+    pub before: Option<Task>,
+    pub after: Task,
+}
+
+impl Change {
+
+    pub fn modify<F: FnOnce(&mut Task)>(task: &Task, f: F) -> Change {
+        let mut after = task.clone();
+        f(&mut after);
+        Change {
+            before: Some(task.clone()),
+            after,
+        }
+    }
+
+    pub fn complete(task: &Task) -> Change {
+        Change::modify(task, |t| t.complete())
+    }
+
+    /// The change that reverts this one. Tasks can't be removed, so added tasks get deleted.
+    pub fn inverse(&self) -> Change {
+        match &self.before {
+            Some(before) => Change {
+                before: Some(self.after.clone()),
+                after: before.clone(),
+            },
+            None => Change::modify(&self.after, |t| t.status = TaskStatus::Deleted),
+        }
+    }
+
+    pub fn kind(&self) -> ChangeKind {
+        let Some(before) = &self.before else {
+            return ChangeKind::Add;
+        };
+        if before.status != self.after.status {
+            return match self.after.status {
+                TaskStatus::Completed => ChangeKind::Complete,
+                TaskStatus::Deleted => ChangeKind::Delete,
+                _ => ChangeKind::Modify,
+            };
+        }
+        if before.sub_of != self.after.sub_of {
+            return ChangeKind::Reparent;
+        }
+        ChangeKind::Modify
+    }
+
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Add,
+    Complete,
+    Delete,
+    Reparent,
+    Modify,
+}
+
+impl std::fmt::Display for ChangeKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let verb = match self {
+            ChangeKind::Add => "adding",
+            ChangeKind::Complete => "completing",
+            ChangeKind::Delete => "deleting",
+            ChangeKind::Reparent => "moving",
+            ChangeKind::Modify => "modifying",
+        };
+        write!(f, "{}", verb)
+    }
+}
+
+/// A `task` invocation, with what to write to its stdin.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaskCommand {
+    pub args: Vec<String>,
+    pub input: Option<String>,
+}
+
+impl TaskCommand {
+
+    fn on(uuid: &Uuid, args: Vec<String>) -> TaskCommand {
+        let mut all = vec![uuid.to_string()];
+        all.extend(args);
+        TaskCommand { args: all, input: None }
+    }
+
+}
+
+/// Fields that are either computed by Taskwarrior or have their own commands
+const SKIPPED_FIELDS: [&str; 10] = [
+    "id", "uuid", "entry", "modified", "end", "urgency", "status", "start", "tags", "annotations",
+];
+
+/// Turns a change into the Taskwarrior commands that make it.
+pub fn commands(change: &Change) -> Result<Vec<TaskCommand>> {
+    let after = &change.after;
+    let Some(before) = &change.before else {
+        // Importing keeps the uuid, which `task add` wouldn't
+        return Ok(vec![TaskCommand {
+            args: vec!["import".to_string()],
+            input: Some(import_json(after)?),
+        }]);
+    };
+    let uuid = &after.uuid;
+
+    let before_value = task_fields(before)?;
+    let after_value = task_fields(after)?;
+
+    let mut modifications = vec![];
+    let mut keys: Vec<&String> = before_value.keys().chain(after_value.keys()).collect();
+    keys.sort();
+    keys.dedup();
+    for key in keys {
+        if SKIPPED_FIELDS.contains(&key.as_str()) {
+            continue;
+        }
+        let old = before_value.get(key);
+        let new = after_value.get(key);
+        if old != new {
+            modifications.push(format!("{}:{}", key, new.map_or_else(String::new, argument)));
+        }
+    }
+    for tag in after.tags.iter().filter(|t| !before.tags.contains(t)) {
+        modifications.push(format!("+{}", tag));
+    }
+    for tag in before.tags.iter().filter(|t| !after.tags.contains(t)) {
+        modifications.push(format!("-{}", tag));
+    }
+
+    let mut commands = vec![];
+    if !modifications.is_empty() {
+        let mut args = vec!["modify".to_string()];
+        args.extend(modifications);
+        commands.push(TaskCommand::on(uuid, args));
+    }
+
+    for annotation in before.annotations.iter().filter(|a| !after.annotations.contains(a)) {
+        commands.push(TaskCommand::on(uuid, vec!["denotate".to_string(), annotation.description.clone()]));
+    }
+    for annotation in after.annotations.iter().filter(|a| !before.annotations.contains(a)) {
+        commands.push(TaskCommand::on(uuid, vec!["annotate".to_string(), annotation.description.clone()]));
+    }
+
+    match (before.start, after.start) {
+        (None, Some(_)) => commands.push(TaskCommand::on(uuid, vec!["start".to_string()])),
+        (Some(_), None) => commands.push(TaskCommand::on(uuid, vec!["stop".to_string()])),
+        _ => {},
+    }
+
+    if before.status != after.status {
+        let args = match after.status {
+            TaskStatus::Completed => vec!["done".to_string()],
+            TaskStatus::Deleted => vec!["delete".to_string()],
+            status => vec!["modify".to_string(), format!("status:{}", argument(&serde_json::to_value(status)?))],
+        };
+        commands.push(TaskCommand::on(uuid, args));
+    }
+
+    Ok(commands)
+}
+
+/// A field that differs between before and after a change
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldChange {
+    pub field: String,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+/// Every field a change touches, except the ones Taskwarrior keeps track of itself.
+pub fn field_changes(change: &Change) -> Result<Vec<FieldChange>> {
+    let before = match &change.before {
+        Some(b) => task_fields(b)?,
+        None => serde_json::Map::new(),
+    };
+    let after = task_fields(&change.after)?;
+    let mut keys: Vec<&String> = before.keys().chain(after.keys()).collect();
+    keys.sort();
+    keys.dedup();
+    Ok(keys.into_iter()
+        .filter(|k| !["id", "uuid", "entry", "modified", "urgency"].contains(&k.as_str()))
+        .filter(|k| before.get(*k) != after.get(*k))
+        .map(|k| FieldChange {
+            field: k.clone(),
+            before: before.get(k).map(argument),
+            after: after.get(k).map(argument),
+        })
+        .collect())
+}
+
+/// Fields of a task as they would be exported, without empty ones
+fn task_fields(task: &Task) -> Result<serde_json::Map<String, Value>> {
+    let Value::Object(mut map) = serde_json::to_value(task)? else {
+        return Err(eyre!("task didn't serialize to an object"));
+    };
+    map.retain(|_, v| !v.is_null() && v.as_array().is_none_or(|a| !a.is_empty()));
+    Ok(map)
+}
+
+fn import_json(task: &Task) -> Result<String> {
+    let mut fields = task_fields(task)?;
+    fields.remove("id");
+    fields.remove("urgency");
+    Ok(serde_json::to_string(&Value::Array(vec![Value::Object(fields)]))?)
+}
+
+/// Value formatted for the command line
+fn argument(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Array(a) => a.iter().map(argument).collect::<Vec<_>>().join(","),
+        // Annotations
+        Value::Object(o) if o.contains_key("description") => argument(&o["description"]),
+        Value::Null => String::new(),
+        v => v.to_string(),
+    }
+}
+
+/// Runs the `task` binary.
+#[derive(Debug, Default)]
+pub struct TaskwarriorBackend;
+
+impl TaskwarriorBackend {
+
+    fn run(command: &TaskCommand) -> Result<()> {
+        let mut child = Command::new("task")
+            // Everything is confirmed by tasksmith before it gets here
+            .args(["rc.confirmation=off", "rc.bulk=0", "rc.recurrence.confirmation=no", "rc.verbose=nothing"])
+            .args(&command.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        if let Some(input) = &command.input {
+            child.stdin.take().ok_or_else(|| eyre!("stdin went missing"))?.write_all(input.as_bytes())?;
+        }
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            return Err(eyre!("`task {}` failed: {}", command.args.join(" "), String::from_utf8_lossy(&output.stderr).trim()));
+        }
+        Ok(())
+    }
+
+}
+
+impl Backend for TaskwarriorBackend {
+
+    fn export(&mut self) -> Result<HashMap<Uuid, Task>> {
+        data::export()
+    }
+
+    fn apply(&mut self, changes: &[Change]) -> Result<()> {
+        for change in changes {
+            for command in commands(change)? {
+                TaskwarriorBackend::run(&command)?;
+            }
+        }
+        Ok(())
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::data::Annotation;
+
+    use super::*;
+
+    fn args(command: &TaskCommand) -> Vec<&str> {
+        command.args.iter().skip(1).map(|a| a.as_str()).collect()
+    }
+
+    fn commands_of(change: &Change) -> Vec<TaskCommand> {
+        commands(change).unwrap()
+    }
+
+    #[test]
+    fn modify_commands() {
+        let mut task = Task::new("Write tests".to_string());
+        task.tags = vec!["work".to_string()];
+        let parent = Uuid::new_v4();
+        let change = Change::modify(&task, |t| {
+            t.description = "Write more tests".to_string();
+            t.project = Some("tasksmith".to_string());
+            t.sub_of = Some(parent);
+            t.tags = vec!["home".to_string()];
+        });
+        let modify = commands_of(&change);
+        assert_eq!(modify.len(), 1);
+        assert_eq!(modify[0].args[0], task.uuid.to_string());
+        assert_eq!(args(&modify[0]), vec![
+            "modify",
+            "description:Write more tests",
+            "project:tasksmith",
+            &format!("sub_of:{}", parent),
+            "+home",
+            "-work",
+        ]);
+
+        let detach = Change::modify(&change.after, |t| t.sub_of = None);
+        assert_eq!(args(&commands_of(&detach)[0]), vec!["modify", "sub_of:"]);
+    }
+
+    #[test]
+    fn status_commands() {
+        let task = Task::new("Finish".to_string());
+        let done = Change::complete(&task);
+        // `end` is set by Taskwarrior
+        assert_eq!(commands_of(&done).len(), 1);
+        assert_eq!(args(&commands_of(&done)[0]), vec!["done"]);
+
+        let undone = Change::modify(&done.after, |t| {
+            t.status = TaskStatus::Pending;
+            t.end = None;
+        });
+        assert_eq!(args(&commands_of(&undone)[0]), vec!["modify", "status:pending"]);
+
+        let annotated = Change::modify(&task, |t| t.annotations.push(Annotation::new("Called back".to_string())));
+        assert_eq!(args(&commands_of(&annotated)[0]), vec!["annotate", "Called back"]);
+        let denotated = annotated.inverse();
+        assert_eq!(args(&commands_of(&denotated)[0]), vec!["denotate", "Called back"]);
+
+        let added = Change { before: None, after: task.clone() };
+        let import = &commands_of(&added)[0];
+        assert_eq!(import.args, vec!["import"]);
+        let json: Value = serde_json::from_str(import.input.as_ref().unwrap()).unwrap();
+        assert_eq!(json[0]["uuid"], task.uuid.to_string());
+        assert!(json[0].get("due").is_none());
+    }
+}
@@ -0,0 +1,168 @@
+use std::{path::PathBuf, rc::Rc};
+
+use color_eyre::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::{data::TaskStatus, ui::glyphs::GlyphSet};
+
+pub type SharedConfig = Rc<Config>;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Config {
+
+    /// Columns of the list, `state`, `description`, `tracked`, or the name of a UDA
+    pub columns: Vec<String>,
+
+    /// UDA to sort tasks by before urgency
+    pub sort: Option<String>,
+
+    /// Draw a progress bar next to the completed count of parent tasks
+    pub progress_bar: bool,
+
+    /// Offer to complete parents once their last subtask is done, and warn about pending
+    /// subtasks when completing a parent
+    pub complete_parents: bool,
+
+    /// Start in dry run mode, where changes are staged until they're written all at once
+    pub dry_run: bool,
+
+    pub kanban: KanbanConfig,
+
+    /// Statuses shown at first, the rest can be toggled on with `f`. Tasks with other statuses
+    /// are still shown when something shown is under them.
+    pub statuses: Vec<TaskStatus>,
+
+    /// Wrap long descriptions onto more lines instead of cutting them off
+    pub wrap: bool,
+
+    /// Show the first annotation of a task under it
+    pub annotation_line: bool,
+
+    /// Lines connecting subtasks to their parent, `off`, `lines`, or `ascii` for terminals
+    /// without box drawing characters
+    pub guides: Guides,
+
+    /// `dark`, `light`, or the name of a theme file in `themes/` next to this config
+    pub theme: String,
+
+    /// Where task rows get their colors, the `theme`, or the `taskrc` color rules
+    pub colors: Colors,
+
+    /// Icons and symbols, `nerd` (needs a nerd font), `unicode` or `ascii`. The
+    /// `TASKSMITH_GLYPHS` environment variable takes precedence.
+    pub glyphs: GlyphSet,
+
+    /// Leave out the templates of recurring tasks, showing their instances on their own
+    pub hide_templates: bool,
+
+    /// Days after tomorrow the agenda shows
+    pub agenda_days: u32,
+
+    pub timewarrior: TimewarriorConfig,
+
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Guides {
+    #[default]
+    Off,
+    Lines,
+    Ascii,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Colors {
+    #[default]
+    Theme,
+    Taskrc,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct KanbanConfig {
+
+    /// What decides the column of a task, `status`, `tag`, or the name of a UDA
+    pub by: String,
+
+    /// Columns from left to right. An empty value is a column for tasks without one.
+    /// Statuses default to pending, waiting and completed, and UDAs to their `values` in the taskrc.
+    pub columns: Vec<String>,
+
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct TimewarriorConfig {
+
+    /// Show time tracked with Timewarrior
+    pub enabled: bool,
+
+    /// Read intervals from this `timew export` output instead of running it
+    pub file: Option<String>,
+
+}
+
+impl Default for KanbanConfig {
+    fn default() -> Self {
+        KanbanConfig {
+            by: "status".to_string(),
+            columns: vec![],
+        }
+    }
+}
+
+impl Config {
+
+    /// Loads `$XDG_CONFIG_HOME/tasksmith/config.json`, using the defaults if it doesn't exist.
+    pub fn load() -> Result<Config> {
+        let Some(path) = Config::path() else {
+            return Ok(Config::default());
+        };
+        if !path.exists() {
+            return Ok(Config::default());
+        }
+        let contents = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&contents)?)
+    }
+
+    pub fn dir() -> Option<PathBuf> {
+        if let Ok(dir) = std::env::var("XDG_CONFIG_HOME") {
+            return Some(PathBuf::from(dir).join("tasksmith"));
+        }
+        std::env::var("HOME").ok().map(|h| PathBuf::from(h).join(".config").join("tasksmith"))
+    }
+
+    fn path() -> Option<PathBuf> {
+        if let Ok(path) = std::env::var("TASKSMITH_CONFIG") {
+            return Some(path.into());
+        }
+        Config::dir().map(|d| d.join("config.json"))
+    }
+
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            columns: vec!["state".to_string(), "description".to_string()],
+            sort: None,
+            progress_bar: false,
+            complete_parents: false,
+            dry_run: false,
+            kanban: KanbanConfig::default(),
+            statuses: vec![TaskStatus::Pending, TaskStatus::Waiting, TaskStatus::Blocked],
+            wrap: true,
+            annotation_line: false,
+            guides: Guides::Off,
+            theme: "dark".to_string(),
+            colors: Colors::Theme,
+            glyphs: GlyphSet::Nerd,
+            hide_templates: false,
+            agenda_days: 5,
+            timewarrior: TimewarriorConfig::default(),
+        }
+    }
+}
@@ -1,5 +1,4 @@
-use chrono::{NaiveDateTime, Utc};
-use ratatui::style::{Color, Style};
+use chrono::{Local, NaiveDateTime, TimeZone, Utc};
 use strum_macros::EnumIter;
 use uuid::Uuid;
 use std::{collections::HashMap, io, process::Command};
@@ -8,10 +7,10 @@ use serde_json::Value;
 use serde::{Deserialize, Serialize};
 use color_eyre::Result;
 
-use crate::util::graph::ParentToChild;
+use crate::{taskrc::{UdaDefinition, UdaSchema, UdaValue}, ui::glyphs::Glyphs, util::graph::{DependsOn, ParentToChild}};
 
 
-mod date_parser {
+pub(crate) mod date_parser {
     use serde::{Serializer, Deserializer};
     use super::*;
 
@@ -35,7 +34,7 @@ mod date_parser {
     }
 }
 
-mod optional_date_parser {
+pub(crate) mod optional_date_parser {
 
     use serde::{Serializer, Deserializer};
     use super::*;
@@ -118,6 +117,39 @@ mod optional_uuid_parser {
 
 }
 
+mod depends_parser {
+    use serde::{Serializer, Deserializer};
+    use super::*;
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Depends {
+        List(Vec<String>),
+        // Taskwarrior < 2.6 exports a comma separated string
+        Joined(String),
+    }
+
+    pub fn serialize<S>(depends: &[Uuid], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let list: Vec<String> = depends.iter().map(|u| u.to_string()).collect();
+        list.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<Uuid>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let list = match Depends::deserialize(deserializer)? {
+            Depends::List(l) => l,
+            Depends::Joined(s) => s.split(',').filter(|s| !s.is_empty()).map(|s| s.to_string()).collect(),
+        };
+        list.iter().map(|s| Uuid::parse_str(s.trim()).map_err(serde::de::Error::custom)).collect()
+    }
+
+}
+
 fn default_time() -> Option<NaiveDateTime> {
     None
 }
@@ -126,12 +158,23 @@ fn default_uuid() -> Option<Uuid> {
     None
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
 pub struct Annotation {
     #[serde(with = "date_parser")]
-    entry: NaiveDateTime,
+    pub entry: NaiveDateTime,
+
+    pub description: String,
+}
+
+impl Annotation {
+
+    pub fn new(description: String) -> Annotation {
+        Annotation {
+            entry: Utc::now().naive_utc(),
+            description,
+        }
+    }
 
-    description: String,
 }
 
 #[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq, PartialOrd, EnumIter, Hash, Eq, Ord)]
@@ -159,38 +202,16 @@ pub enum TaskStatus {
 
 impl TaskStatus {
 
-    pub fn get_display(&self, task: &Task) -> (String, Style){
+    /// Icon for the state column, the theme decides its style
+    pub fn get_display(&self, task: &Task, glyphs: &Glyphs) -> String {
         match self {
-            TaskStatus::Blocked => {
-                ("".to_string(), Style::default().fg(Color::Blue))
-            },
-            TaskStatus::Completed => {
-                ("".to_string(), Style::default().fg(Color::Blue))
-            },
-            TaskStatus::Waiting => {
-                ("".to_string(), Style::default().fg(Color::Blue))
-            },
-            TaskStatus::Deleted => {
-                ("".to_string(), Style::default().fg(Color::Gray))
-            },
-            TaskStatus::Recurring => {
-                ("".to_string(), Style::default().fg(Color::Blue))
-            },
-            TaskStatus::Pending => {
-                let urgency = task.urgency;
-                let block = if urgency > 9.0 {
-                    "◼◼◼"
-                } else if urgency > 6.0 {
-                    "◼◼"
-                } else if urgency > 3.0 {
-                    "◼"
-                } else {
-                    ""
-                };
-                (block.to_string(), Style::default().fg(Color::Red))
-            }
+            TaskStatus::Blocked => glyphs.status_blocked.to_string(),
+            TaskStatus::Completed => glyphs.status_completed.to_string(),
+            TaskStatus::Waiting => glyphs.status_waiting.to_string(),
+            TaskStatus::Deleted => glyphs.status_deleted.to_string(),
+            TaskStatus::Recurring => glyphs.status_recurring.to_string(),
+            TaskStatus::Pending => glyphs.urgency.repeat(task.urgency_level()),
         }
-
     }
 
 }
@@ -218,6 +239,13 @@ pub struct Task {
     #[serde(with = "optional_date_parser", default = "default_time")]
     pub due: Option<NaiveDateTime>,
 
+    #[serde(with = "optional_date_parser", default = "default_time")]
+    pub scheduled: Option<NaiveDateTime>,
+
+    /// Hidden as waiting until then
+    #[serde(with = "optional_date_parser", default = "default_time")]
+    pub wait: Option<NaiveDateTime>,
+
     #[serde(with = "optional_date_parser", default = "default_time")]
     pub start: Option<NaiveDateTime>,
 
@@ -233,6 +261,9 @@ pub struct Task {
 
     pub project: Option<String>,
 
+    /// How often a recurring task comes back, on both the template and its instances
+    pub recur: Option<String>,
+
     pub mask: Option<String>,
 
     #[serde(rename = "imask")]
@@ -244,6 +275,9 @@ pub struct Task {
     #[serde(with = "optional_uuid_parser", default = "default_uuid")]
     pub sub_of: Option<Uuid>,
 
+    #[serde(with = "depends_parser", default = "Vec::new")]
+    pub depends: Vec<Uuid>,
+
     #[serde(default = "Vec::new")]
     pub annotations: Vec<Annotation>,
 
@@ -260,20 +294,76 @@ impl Task {
             entry: Utc::now().naive_local(),
             modified: Utc::now().naive_local(),
             due: None,
+            scheduled: None,
+            wait: None,
             start: None,
             end: None,
             status: TaskStatus::Pending,
             tags: vec![],
             urgency: 1.0,
             project: None,
+            recur: None,
             mask: None,
             mask_index: None,
             parent: None,
             sub_of: None,
+            depends: vec![],
             annotations: vec![],
             udas: HashMap::default(),
         }
     }
+
+    pub fn complete(&mut self) {
+        self.status = TaskStatus::Completed;
+        self.end = Some(Utc::now().naive_utc());
+    }
+
+    pub fn is_resolved(&self) -> bool {
+        matches!(self.status, TaskStatus::Completed | TaskStatus::Deleted)
+    }
+
+    /// Starts or stops working on the task
+    pub fn toggle_start(&mut self) {
+        self.start = match self.start {
+            Some(_) => None,
+            None => Some(Utc::now().naive_utc()),
+        };
+    }
+
+    /// From 0 to 3 by the urgency Taskwarrior gave it
+    pub fn urgency_level(&self) -> usize {
+        if self.urgency > 9.0 {
+            3
+        } else if self.urgency > 6.0 {
+            2
+        } else if self.urgency > 3.0 {
+            1
+        } else {
+            0
+        }
+    }
+
+    /// Local days until the task is due, negative once it's overdue
+    pub fn days_until_due(&self, now: NaiveDateTime) -> Option<i64> {
+        self.due.map(|due| (to_local(&due).date() - to_local(&now).date()).num_days())
+    }
+
+    /// How long the task has been started for
+    pub fn elapsed(&self, now: NaiveDateTime) -> Option<chrono::Duration> {
+        self.start.map(|start| now - start)
+    }
+
+    /// Value of a UDA typed by its definition in the schema. This is the typed interface to
+    /// UDAs, match on the `UdaValue` for a string, number, date, duration or uuid.
+    ///
+    /// UDAs that aren't defined in the schema are treated as strings.
+    pub fn uda(&self, schema: &UdaSchema, name: &str) -> Option<UdaValue> {
+        let value = self.udas.get(name)?;
+        match schema.get(name) {
+            Some(definition) => definition.parse(value),
+            None => UdaDefinition::new(name.to_string()).parse(value),
+        }
+    }
 }
 
 impl ParentToChild for Task {
@@ -292,6 +382,18 @@ impl ParentToChild for Task {
 
 }
 
+impl DependsOn for Task {
+
+    fn depends(&self) -> &[Uuid] {
+        &self.depends
+    }
+
+    fn is_resolved(&self) -> bool {
+        Task::is_resolved(self)
+    }
+
+}
+
 pub fn from_json(val: Value) -> Result<HashMap<Uuid, Task>> {
     let mut task_map: HashMap<Uuid, Task> = HashMap::default();
     for el in val.as_array().unwrap() {
@@ -301,13 +403,62 @@ pub fn from_json(val: Value) -> Result<HashMap<Uuid, Task>> {
     Ok(task_map)
 }
 
+fn export_json() -> Result<String> {
+    let output = Command::new("task").arg("export").output()?;
+    let contents = String::from_utf8_lossy(&output.stdout);
+    Ok(contents.chars().filter(|c| !c.is_control()).collect())
+}
+
+/// Elapsed time as `h:mm:ss`
+pub fn format_elapsed(elapsed: chrono::Duration) -> String {
+    let seconds = elapsed.num_seconds().max(0);
+    format!("{}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60)
+}
+
+/// How long ago, or how long until, a date is, roughly
+pub fn format_relative(date: NaiveDateTime, now: NaiveDateTime) -> String {
+    let seconds = (now - date).num_seconds();
+    let amount = seconds.abs();
+    let amount = if amount < 60 {
+        return "just now".to_string();
+    } else if amount < 3600 {
+        format!("{}m", amount / 60)
+    } else if amount < 86400 {
+        format!("{}h", amount / 3600)
+    } else if amount < 86400 * 60 {
+        format!("{}d", amount / 86400)
+    } else if amount < 86400 * 365 {
+        format!("{}mo", amount / (86400 * 30))
+    } else {
+        format!("{}y", amount / (86400 * 365))
+    };
+    if seconds < 0 {
+        format!("in {}", amount)
+    } else {
+        format!("{} ago", amount)
+    }
+}
+
+/// Taskwarrior dates are in UTC
+pub fn to_local(date: &NaiveDateTime) -> NaiveDateTime {
+    Utc.from_utc_datetime(date).with_timezone(&Local).naive_local()
+}
+
+/// Direct subtasks of a task
+pub fn children<'a>(tasks: &'a HashMap<Uuid, Task>, uuid: &'a Uuid) -> impl Iterator<Item = &'a Task> {
+    tasks.values().filter(move |t| t.sub_of.as_ref() == Some(uuid))
+}
+
+/// Tasks straight from Taskwarrior, even if tasks were piped in at the start
+pub fn export() -> Result<HashMap<Uuid, Task>> {
+    let json: Value = serde_json::from_str(&export_json()?)?;
+    from_json(json)
+}
+
 pub fn get_tasks() -> Result<HashMap<Uuid, Task>> {
 
     let s = if atty::is(atty::Stream::Stdin) {
-        let output = Command::new("task").arg("export").output()?;
-        let contents = String::from_utf8_lossy(&output.stdout);
-        let s: String = contents.chars().filter(|c| !c.is_control()).collect();
-        s
+        export_json()?
     } else {
         let lines: Vec<_> = io::stdin().lines().map(|l| l.unwrap()).collect();
         lines.join("\n")
@@ -323,3 +474,21 @@ pub fn get_tasks() -> Result<HashMap<Uuid, Task>> {
     Ok(result)
 }
 
+
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+
+    #[test]
+    fn relative_dates() {
+        let now = Utc::now().naive_utc();
+        assert_eq!(format_relative(now - Duration::seconds(20), now), "just now");
+        assert_eq!(format_relative(now - Duration::minutes(5), now), "5m ago");
+        assert_eq!(format_relative(now - Duration::hours(26), now), "1d ago");
+        assert_eq!(format_relative(now + Duration::days(3), now), "in 3d");
+        assert_eq!(format_relative(now - Duration::days(400), now), "1y ago");
+    }
+}
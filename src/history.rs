@@ -0,0 +1,147 @@
+use crate::backend::Change;
+
+/// Batches of changes made through tasksmith, so they can be undone without `task undo`
+/// (which undoes whatever was changed last, even outside of tasksmith).
+#[derive(Debug, Default)]
+pub struct History {
+    undo: Vec<Vec<Change>>,
+    redo: Vec<Vec<Change>>,
+}
+
+impl History {
+
+    pub fn record(&mut self, changes: Vec<Change>) {
+        if changes.is_empty() {
+            return;
+        }
+        self.undo.push(changes);
+        self.redo.clear();
+    }
+
+    /// Changes reverting the last batch, in reverse order. Nothing moves to the redo stack until
+    /// they've been written, see `undone`.
+    pub fn undo(&self) -> Option<Vec<Change>> {
+        let batch = self.undo.last()?;
+        Some(batch.iter().rev().map(|c| c.inverse()).collect())
+    }
+
+    /// The first `count` changes from `undo` were written. Those are the last ones of the batch,
+    /// they can be redone now and the rest can still be undone.
+    pub fn undone(&mut self, count: usize) {
+        let Some(mut batch) = self.undo.pop() else {
+            return;
+        };
+        let reverted = batch.split_off(batch.len() - count.min(batch.len()));
+        if !batch.is_empty() {
+            self.undo.push(batch);
+        }
+        if !reverted.is_empty() {
+            self.redo.push(reverted);
+        }
+    }
+
+    /// Changes of the last undone batch. Nothing moves back to the undo stack until they've been
+    /// written, see `redone`.
+    pub fn redo(&self) -> Option<Vec<Change>> {
+        self.redo.last().cloned()
+    }
+
+    /// The first `count` changes from `redo` were written, and can be undone again.
+    pub fn redone(&mut self, count: usize) {
+        let Some(mut batch) = self.redo.pop() else {
+            return;
+        };
+        let rest = batch.split_off(count.min(batch.len()));
+        if !batch.is_empty() {
+            self.undo.push(batch);
+        }
+        if !rest.is_empty() {
+            self.redo.push(rest);
+        }
+    }
+
+    pub fn next_undo(&self) -> Option<&[Change]> {
+        self.undo.last().map(|b| b.as_slice())
+    }
+
+    pub fn next_redo(&self) -> Option<&[Change]> {
+        self.redo.last().map(|b| b.as_slice())
+    }
+
+}
+
+/// What a batch did, like `completing "Write docs"` or `deleting 3 tasks`
+pub fn describe(changes: &[Change]) -> String {
+    match changes {
+        [] => "nothing".to_string(),
+        [change] => format!("{} \"{}\"", change.kind(), change.after.description),
+        [first, ..] => format!("{} {} tasks", first.kind(), changes.len()),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::data::{Task, TaskStatus};
+
+    use super::*;
+
+    #[test]
+    fn undo_redo() {
+        let task = Task::new("Undo me".to_string());
+        let added = Change { before: None, after: task.clone() };
+        let completed = Change::complete(&task);
+
+        let mut history = History::default();
+        history.record(vec![added]);
+        history.record(vec![completed]);
+
+        let undo = history.undo().unwrap();
+        assert_eq!(undo[0].after.status, TaskStatus::Pending);
+        assert_eq!(undo[0].before.as_ref().unwrap().status, TaskStatus::Completed);
+        // Still there until it's written
+        assert!(history.next_redo().is_none());
+        history.undone(undo.len());
+        assert_eq!(describe(history.next_redo().unwrap()), "completing \"Undo me\"");
+
+        // Undoing an add deletes the task
+        let undo = history.undo().unwrap();
+        assert_eq!(undo[0].after.status, TaskStatus::Deleted);
+        history.undone(undo.len());
+        assert!(history.undo().is_none());
+
+        let redo = history.redo().unwrap();
+        assert!(redo[0].before.is_none());
+        history.redone(redo.len());
+
+        // New changes clear what could be redone
+        history.record(vec![Change::complete(&task)]);
+        assert!(history.redo().is_none());
+    }
+
+    #[test]
+    fn partly_written() {
+        let tasks: Vec<Task> = ["One", "Two", "Three"].iter().map(|d| Task::new(d.to_string())).collect();
+        let batch: Vec<Change> = tasks.iter().map(Change::complete).collect();
+        let mut history = History::default();
+        history.record(batch);
+
+        // Only the last change got reverted
+        history.undone(1);
+        assert_eq!(history.next_undo().unwrap().len(), 2);
+        assert_eq!(history.next_redo().unwrap()[0].after.description, "Three");
+        // The rest of the undo starts from where it stopped
+        assert_eq!(history.undo().unwrap()[0].after.description, "Two");
+
+        history.undone(2);
+        assert!(history.next_undo().is_none());
+        assert_eq!(describe(history.next_redo().unwrap()), "completing 2 tasks");
+
+        // Redoing "One" and failing on "Two" leaves "Two" and then "Three" to redo
+        history.redone(1);
+        assert_eq!(describe(history.next_undo().unwrap()), "completing \"One\"");
+        assert_eq!(describe(history.next_redo().unwrap()), "completing \"Two\"");
+        history.redone(1);
+        assert_eq!(describe(history.next_redo().unwrap()), "completing \"Three\"");
+    }
+}
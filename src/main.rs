@@ -6,10 +6,17 @@ use event::{Event, EventHandler};
 use ratatui::{backend::CrosstermBackend, Terminal};
 
 mod data;
+mod action;
+mod backend;
+mod config;
+mod taskrc;
+mod timew;
 mod util;
 mod ui;
 mod app;
 mod event;
+mod history;
+mod staging;
 mod tabs;
 
 
@@ -35,7 +42,7 @@ fn run(
 
             let event = events.next()?;
             match event {
-                Event::Tick => {},
+                Event::Tick => app.tick(),
                 _ => {
                     app.event(event)
                 },
@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+
+use uuid::Uuid;
+
+use crate::{backend::{self, Change}, data::Task};
+
+/// Changes that have only been made in memory, waiting to be written all at once.
+#[derive(Debug)]
+pub struct Staging {
+    /// Tasks as they were in the last export
+    exported: HashMap<Uuid, Task>,
+    changes: Vec<Change>,
+}
+
+impl Staging {
+
+    pub fn new(exported: HashMap<Uuid, Task>) -> Staging {
+        Staging {
+            exported,
+            changes: vec![],
+        }
+    }
+
+    pub fn stage(&mut self, tasks: &mut HashMap<Uuid, Task>, changes: &[Change]) {
+        for change in changes {
+            tasks.insert(change.after.uuid, change.after.clone());
+        }
+        self.changes.extend_from_slice(changes);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// One change per task, from how it was exported to how it is now.
+    pub fn coalesced(&self) -> Vec<Change> {
+        let mut order: Vec<Uuid> = vec![];
+        let mut merged: HashMap<Uuid, Change> = HashMap::new();
+        for change in &self.changes {
+            let uuid = change.after.uuid;
+            match merged.get_mut(&uuid) {
+                Some(existing) => existing.after = change.after.clone(),
+                None => {
+                    order.push(uuid);
+                    merged.insert(uuid, Change {
+                        before: self.exported.get(&uuid).cloned(),
+                        after: change.after.clone(),
+                    });
+                },
+            }
+        }
+        order.into_iter()
+            .filter_map(|u| merged.remove(&u))
+            // Changed and then changed back
+            .filter(|c| backend::field_changes(c).map_or(true, |f| !f.is_empty()))
+            .collect()
+    }
+
+    /// Throws away every staged change, giving back the last export.
+    pub fn discard(&mut self) -> HashMap<Uuid, Task> {
+        self.changes.clear();
+        self.exported.clone()
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn coalesce_and_discard() {
+        let task = Task::new("Stage me".to_string());
+        let other = Task::new("Leave me".to_string());
+        let exported: HashMap<Uuid, Task> = vec![task.clone(), other.clone()].into_iter().map(|t| (t.uuid, t)).collect();
+        let mut tasks = exported.clone();
+        let mut staging = Staging::new(exported);
+
+        let renamed = Change::modify(&task, |t| t.description = "Staged".to_string());
+        let completed = Change::complete(&renamed.after);
+        staging.stage(&mut tasks, &[renamed]);
+        staging.stage(&mut tasks, &[completed]);
+        // Changed and changed back again
+        let project = Change::modify(&other, |t| t.project = Some("tasksmith".to_string()));
+        let back = Change::modify(&project.after, |t| t.project = None);
+        staging.stage(&mut tasks, &[project, back]);
+
+        assert_eq!(tasks.get(&task.uuid).unwrap().description, "Staged");
+        let coalesced = staging.coalesced();
+        assert_eq!(coalesced.len(), 1);
+        assert_eq!(coalesced[0].before.as_ref().unwrap().description, "Stage me");
+        let fields: Vec<String> = backend::field_changes(&coalesced[0]).unwrap().into_iter().map(|f| f.field).collect();
+        assert_eq!(fields, vec!["description", "end", "status"]);
+
+        let tasks = staging.discard();
+        assert_eq!(tasks.get(&task.uuid).unwrap().description, "Stage me");
+        assert!(staging.is_empty());
+    }
+}
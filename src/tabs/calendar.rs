@@ -0,0 +1,312 @@
+use std::collections::HashMap;
+
+use chrono::{Datelike, Duration, Local, Months, NaiveDate, NaiveDateTime, Utc};
+use color_eyre::Result;
+use crossterm::event::KeyCode;
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    text::{Line, Span},
+    widgets::{Block, Borders, Widget},
+    Frame,
+};
+use uuid::Uuid;
+
+use crate::{
+    action::Action,
+    backend::Change,
+    data::{self, Task},
+    event::Event,
+    ui::{prompt::Prompt, row, style::SharedTheme},
+};
+
+use super::Tab;
+
+const WEEKDAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DateField {
+    Due,
+    Scheduled,
+}
+
+impl DateField {
+
+    fn get(&self, task: &Task) -> Option<NaiveDateTime> {
+        match self {
+            DateField::Due => task.due,
+            DateField::Scheduled => task.scheduled,
+        }
+    }
+
+    fn set(&self, task: &mut Task, date: NaiveDateTime) {
+        match self {
+            DateField::Due => task.due = Some(date),
+            DateField::Scheduled => task.scheduled = Some(date),
+        }
+    }
+
+    fn name(&self) -> &str {
+        match self {
+            DateField::Due => "due",
+            DateField::Scheduled => "scheduled",
+        }
+    }
+
+}
+
+/// A task on a day, it can be there twice if it's both due and scheduled that day
+#[derive(Debug, Clone, Copy)]
+struct Entry {
+    task: Uuid,
+    field: DateField,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Focus {
+    Days,
+    Tasks,
+}
+
+/// Month view of when tasks are due or scheduled. Tasks get rescheduled by picking them up
+/// from a day (`m`) and dropping them on another (Enter).
+#[derive(Debug)]
+pub struct Calendar {
+    selected: NaiveDate,
+    days: HashMap<NaiveDate, Vec<Entry>>,
+    focus: Focus,
+    entry: usize,
+    /// Entry being moved and the day it came from
+    moving: Option<(Entry, NaiveDate)>,
+}
+
+impl Calendar {
+
+    pub fn new(tasks: &HashMap<Uuid, Task>) -> Calendar {
+        let mut calendar = Calendar {
+            selected: Local::now().date_naive(),
+            days: HashMap::new(),
+            focus: Focus::Days,
+            entry: 0,
+            moving: None,
+        };
+        calendar.refresh(tasks);
+        calendar
+    }
+
+    fn entries(&self, day: &NaiveDate) -> &[Entry] {
+        self.days.get(day).map_or(&[], |e| e.as_slice())
+    }
+
+    fn move_day(&mut self, days: i64) {
+        self.selected += Duration::days(days);
+        self.entry = 0;
+    }
+
+    fn move_month(&mut self, months: i32) {
+        let shifted = if months < 0 {
+            self.selected.checked_sub_months(Months::new(months.unsigned_abs()))
+        } else {
+            self.selected.checked_add_months(Months::new(months as u32))
+        };
+        if let Some(date) = shifted {
+            self.selected = date;
+            self.entry = 0;
+        }
+    }
+
+    fn move_entry(&mut self, change: isize) {
+        let len = self.entries(&self.selected).len();
+        self.entry = (self.entry as isize + change).clamp(0, len.saturating_sub(1) as isize) as usize;
+    }
+
+    fn pick_up(&mut self) {
+        if let Some(entry) = self.entries(&self.selected).get(self.entry) {
+            self.moving = Some((*entry, self.selected));
+            self.focus = Focus::Days;
+        }
+    }
+
+    /// Asks to move the picked up task to the selected day, keeping its time
+    fn drop_task(&mut self, tasks: &HashMap<Uuid, Task>) -> Vec<Action> {
+        let Some((entry, from)) = self.moving.take() else {
+            return vec![];
+        };
+        let Some(task) = tasks.get(&entry.task) else {
+            return vec![];
+        };
+        let Some(date) = entry.field.get(task) else {
+            return vec![];
+        };
+        let shift = self.selected - from;
+        if shift.is_zero() {
+            return vec![];
+        }
+        let change = Change::modify(task, |t| entry.field.set(t, date + shift));
+        vec![Action::Prompt(Prompt::confirm(
+            "Reschedule",
+            format!("Move {} of \"{}\" to {}?", entry.field.name(), task.description, self.selected.format("%Y-%m-%d")),
+            vec![Action::Apply(vec![change])],
+        ))]
+    }
+
+    fn draw_month(&self, theme: &SharedTheme, frame: &mut Frame, area: Rect) {
+        let first = self.selected.with_day(1).expect("every month has a first");
+        let title = format!(" {} (d: due, s: scheduled) ", first.format("%B %Y"));
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_set(theme.glyphs().border)
+            .border_style(if self.focus == Focus::Days { theme.text() } else { theme.border() })
+            .title(Span::styled(title, theme.text()));
+        let inner = block.inner(area);
+        block.render(area, frame.buffer_mut());
+        if inner.height < 2 || inner.width < 7 {
+            return;
+        }
+
+        let buf = frame.buffer_mut();
+        let cell_width = inner.width / 7;
+        for (i, day) in WEEKDAYS.iter().enumerate() {
+            buf.set_line(inner.x + i as u16 * cell_width, inner.y, &Line::styled(*day, theme.fold()), cell_width);
+        }
+
+        let weeks = 6;
+        let cell_height = ((inner.height - 1) / weeks).max(1);
+        let start = first - Duration::days(first.weekday().num_days_from_monday() as i64);
+        let today = Local::now().date_naive();
+        for week in 0..weeks {
+            for weekday in 0..7 {
+                let day = start + Duration::days((week * 7 + weekday) as i64);
+                let y = inner.y + 1 + week * cell_height;
+                if y >= inner.bottom() {
+                    continue;
+                }
+                let cell = Rect::new(inner.x + weekday * cell_width, y, cell_width, cell_height.min(inner.bottom() - y));
+                self.draw_day(theme, buf, cell, day, today);
+            }
+        }
+    }
+
+    fn draw_day(&self, theme: &SharedTheme, buf: &mut ratatui::buffer::Buffer, cell: Rect, day: NaiveDate, today: NaiveDate) {
+        if day == self.selected {
+            buf.set_style(cell, theme.cursor());
+        } else if self.moving.is_some_and(|(_, from)| from == day) {
+            buf.set_style(cell, theme.highlight());
+        }
+        let style = if day.month() != self.selected.month() {
+            theme.border()
+        } else if day == today {
+            theme.marker()
+        } else {
+            theme.text()
+        };
+        buf.set_line(cell.x, cell.y, &Line::styled(format!("{:>2}", day.day()), style), cell.width);
+
+        let entries = self.entries(&day);
+        if entries.is_empty() {
+            return;
+        }
+        let due = entries.iter().filter(|e| e.field == DateField::Due).count();
+        let scheduled = entries.len() - due;
+        let mut counts = vec![];
+        if due > 0 {
+            counts.push(Span::styled(format!("{}d ", due), theme.marker()));
+        }
+        if scheduled > 0 {
+            counts.push(Span::styled(format!("{}s", scheduled), theme.fold()));
+        }
+        let (x, y) = if cell.height > 1 { (cell.x, cell.y + 1) } else { (cell.x + 3, cell.y) };
+        buf.set_line(x, y, &Line::from(counts), cell.right() - x);
+    }
+
+    fn draw_tasks(&self, theme: &SharedTheme, frame: &mut Frame, area: Rect, tasks: &HashMap<Uuid, Task>) {
+        let title = format!(" {} ", self.selected.format("%A %Y-%m-%d"));
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_set(theme.glyphs().border)
+            .border_style(if self.focus == Focus::Tasks { theme.text() } else { theme.border() })
+            .title(Span::styled(title, theme.text()));
+        let inner = block.inner(area);
+        block.render(area, frame.buffer_mut());
+
+        let buf = frame.buffer_mut();
+        if let Some((entry, _)) = self.moving {
+            if let Some(task) = tasks.get(&entry.task) {
+                let line = Line::styled(format!("Moving: {}", task.description), theme.marker());
+                buf.set_line(inner.x, inner.y, &line, inner.width);
+            }
+            return;
+        }
+        let now = Utc::now().naive_utc();
+        for (i, entry) in self.entries(&self.selected).iter().enumerate().take(inner.height as usize) {
+            let Some(task) = tasks.get(&entry.task) else {
+                continue;
+            };
+            let y = inner.y + i as u16;
+            if self.focus == Focus::Tasks && i == self.entry {
+                buf.set_style(Rect::new(inner.x, y, inner.width, 1), theme.cursor());
+            }
+            let line = Line::from(vec![
+                Span::styled(format!("{:<4}", &entry.field.name()[..3]), theme.fold()),
+                Span::styled(task.description.clone(), row::task::description_style(task, theme, now)),
+            ]);
+            buf.set_line(inner.x, y, &line, inner.width);
+        }
+    }
+
+}
+
+impl Tab for Calendar {
+
+    fn title(&self) -> String {
+        "Calendar".to_string()
+    }
+
+    fn draw(&mut self, theme: SharedTheme, frame: &mut Frame, area: Rect, tasks: &HashMap<Uuid, Task>) -> Result<()> {
+        let layout = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Fill(3), Constraint::Fill(2)])
+            .split(area);
+        self.draw_month(&theme, frame, layout[0]);
+        self.draw_tasks(&theme, frame, layout[1], tasks);
+        Ok(())
+    }
+
+    fn event(&mut self, event: Event, tasks: &HashMap<Uuid, Task>) -> Vec<Action> {
+        let Event::Key(k) = event else {
+            return vec![];
+        };
+        match (self.focus, k.code) {
+            (Focus::Days, KeyCode::Char('h')) => self.move_day(-1),
+            (Focus::Days, KeyCode::Char('l')) => self.move_day(1),
+            (Focus::Days, KeyCode::Char('j')) => self.move_day(7),
+            (Focus::Days, KeyCode::Char('k')) => self.move_day(-7),
+            (Focus::Days, KeyCode::Char('H')) => self.move_month(-1),
+            (Focus::Days, KeyCode::Char('L')) => self.move_month(1),
+            (Focus::Days, KeyCode::Enter) if self.moving.is_some() => return self.drop_task(tasks),
+            (Focus::Days, KeyCode::Enter) if !self.entries(&self.selected).is_empty() => self.focus = Focus::Tasks,
+            (Focus::Days, KeyCode::Esc) => self.moving = None,
+            (Focus::Tasks, KeyCode::Char('j')) => self.move_entry(1),
+            (Focus::Tasks, KeyCode::Char('k')) => self.move_entry(-1),
+            (Focus::Tasks, KeyCode::Char('m')) => self.pick_up(),
+            (Focus::Tasks, KeyCode::Esc) => self.focus = Focus::Days,
+            _ => {}
+        }
+        vec![]
+    }
+
+    fn refresh(&mut self, tasks: &HashMap<Uuid, Task>) {
+        self.days.clear();
+        let mut sorted: Vec<&Task> = tasks.values().filter(|t| !t.is_resolved()).collect();
+        sorted.sort_by(|a, b| b.urgency.total_cmp(&a.urgency));
+        for task in sorted {
+            for field in [DateField::Due, DateField::Scheduled] {
+                if let Some(date) = field.get(task) {
+                    let day = data::to_local(&date).date();
+                    self.days.entry(day).or_default().push(Entry { task: task.uuid, field });
+                }
+            }
+        }
+        self.move_entry(0);
+    }
+
+}
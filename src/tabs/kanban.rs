@@ -0,0 +1,411 @@
+use std::collections::HashMap;
+
+use chrono::{NaiveDate, NaiveDateTime, Utc};
+use color_eyre::Result;
+use crossterm::event::{KeyCode, MouseButton, MouseEvent, MouseEventKind};
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    text::{Line, Span},
+    widgets::{Block, Borders, Widget},
+    Frame,
+};
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::{
+    action::Action,
+    backend::Change,
+    config::KanbanConfig,
+    data::{Task, TaskStatus},
+    event::Event,
+    taskrc::SharedUdas,
+    ui::{prompt::Prompt, row, style::SharedTheme},
+};
+
+use super::Tab;
+
+const CARD_HEIGHT: u16 = 3;
+
+/// What the columns of the board are
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum BoardField {
+    Status,
+    Tag,
+    Uda(String),
+}
+
+#[derive(Debug, Clone)]
+struct Column {
+    value: String,
+    tasks: Vec<Uuid>,
+}
+
+#[derive(Debug)]
+pub struct Kanban {
+    field: BoardField,
+    columns: Vec<Column>,
+    column: usize,
+    card: usize,
+    /// Inside of each column when last drawn, and the first card shown in it
+    areas: Vec<(Rect, usize)>,
+}
+
+impl Kanban {
+
+    pub fn new(tasks: &HashMap<Uuid, Task>, config: &KanbanConfig, udas: SharedUdas) -> Kanban {
+        let field = match config.by.as_str() {
+            "status" => BoardField::Status,
+            "tag" | "tags" => BoardField::Tag,
+            uda => BoardField::Uda(uda.to_string()),
+        };
+        let values = if !config.columns.is_empty() {
+            config.columns.clone()
+        } else {
+            match &field {
+                BoardField::Status => vec!["pending".to_string(), "waiting".to_string(), "completed".to_string()],
+                BoardField::Tag => vec![],
+                BoardField::Uda(name) => udas.get(name).map(|u| u.values.clone()).unwrap_or_default(),
+            }
+        };
+        let mut kanban = Kanban {
+            field,
+            columns: values.into_iter().map(|value| Column { value, tasks: vec![] }).collect(),
+            column: 0,
+            card: 0,
+            areas: vec![],
+        };
+        kanban.refresh(tasks);
+        kanban
+    }
+
+    /// The column value of a task, if it's on the board at all
+    fn value(&self, task: &Task) -> Option<String> {
+        match &self.field {
+            BoardField::Status => {
+                match serde_json::to_value(task.status).ok()? {
+                    Value::String(s) => Some(s),
+                    _ => None,
+                }
+            },
+            _ if task.is_resolved() => None,
+            BoardField::Tag => {
+                let tagged = self.columns.iter().find(|c| task.tags.contains(&c.value));
+                Some(tagged.map_or_else(String::new, |c| c.value.clone()))
+            },
+            BoardField::Uda(name) => {
+                Some(task.udas.get(name).and_then(|v| v.as_str()).unwrap_or_default().to_string())
+            },
+        }
+    }
+
+    fn selected(&self) -> Option<Uuid> {
+        self.columns.get(self.column)?.tasks.get(self.card).copied()
+    }
+
+    fn select_column(&mut self, change: isize) {
+        let column = self.column as isize + change;
+        if column >= 0 && (column as usize) < self.columns.len() {
+            self.column = column as usize;
+            self.clamp_card();
+        }
+    }
+
+    fn select_card(&mut self, change: isize) {
+        self.card = (self.card as isize + change).max(0) as usize;
+        self.clamp_card();
+    }
+
+    fn clamp_card(&mut self) {
+        let len = self.columns.get(self.column).map_or(0, |c| c.tasks.len());
+        self.card = self.card.min(len.saturating_sub(1));
+    }
+
+    /// Moves the selected task to the next column over, after asking
+    fn move_task(&self, tasks: &HashMap<Uuid, Task>, change: isize) -> Vec<Action> {
+        let Some(task) = self.selected().and_then(|u| tasks.get(&u)) else {
+            return vec![];
+        };
+        let target = self.column as isize + change;
+        if target < 0 || target as usize >= self.columns.len() {
+            return vec![];
+        }
+        let from = &self.columns[self.column].value;
+        let to = &self.columns[target as usize].value;
+        let Some(change) = self.set_value(task, from, to) else {
+            return vec![];
+        };
+        vec![Action::Prompt(Prompt::confirm(
+            "Move",
+            format!("Move \"{}\" to {}?", task.description, column_title(to)),
+            vec![Action::Apply(vec![change])],
+        ))]
+    }
+
+    fn set_value(&self, task: &Task, from: &str, to: &str) -> Option<Change> {
+        match &self.field {
+            BoardField::Status => {
+                let status: TaskStatus = serde_json::from_value(Value::String(to.to_string())).ok()?;
+                let now = Utc::now().naive_utc();
+                Some(Change::modify(task, |t| {
+                    // Taskwarrior only keeps tasks waiting while their wait date is ahead
+                    t.wait = match status {
+                        TaskStatus::Waiting => t.wait.filter(|wait| *wait > now).or_else(someday),
+                        _ => None,
+                    };
+                    if status == TaskStatus::Completed {
+                        t.complete();
+                    } else {
+                        t.status = status;
+                        t.end = None;
+                    }
+                }))
+            },
+            BoardField::Tag => Some(Change::modify(task, |t| {
+                t.tags.retain(|tag| tag != from);
+                if !to.is_empty() {
+                    t.tags.push(to.to_string());
+                }
+            })),
+            BoardField::Uda(name) => Some(Change::modify(task, |t| {
+                if to.is_empty() {
+                    t.udas.remove(name);
+                } else {
+                    t.udas.insert(name.clone(), Value::String(to.to_string()));
+                }
+            })),
+        }
+    }
+
+    /// Clicking selects a column and the card under the mouse, and the wheel moves through cards
+    fn mouse(&mut self, event: MouseEvent) {
+        match event.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                let Some((column, (area, offset))) = self.areas.iter().enumerate().find(|(_, (area, _))| {
+                    (area.left()..area.right()).contains(&event.column) && (area.top()..area.bottom()).contains(&event.row)
+                }) else {
+                    return;
+                };
+                self.card = offset + ((event.row - area.y) / CARD_HEIGHT) as usize;
+                self.column = column;
+                self.clamp_card();
+            },
+            MouseEventKind::ScrollDown => self.select_card(1),
+            MouseEventKind::ScrollUp => self.select_card(-1),
+            _ => {},
+        }
+    }
+
+    /// Returns the inside of the column and the first card shown
+    fn draw_column(&self, theme: &SharedTheme, frame: &mut Frame, area: Rect, index: usize, tasks: &HashMap<Uuid, Task>) -> (Rect, usize) {
+        let column = &self.columns[index];
+        let selected = index == self.column;
+        let title = format!(" {} ({}) ", column_title(&column.value), column.tasks.len());
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_set(theme.glyphs().border)
+            .border_style(if selected { theme.text() } else { theme.border() })
+            .title(Span::styled(title, theme.text()));
+        let inner = block.inner(area);
+        block.render(area, frame.buffer_mut());
+
+        // Keep the selected card in view
+        let visible = (inner.height / CARD_HEIGHT).max(1) as usize;
+        let offset = if selected && self.card >= visible { self.card + 1 - visible } else { 0 };
+
+        let buf = frame.buffer_mut();
+        let now = Utc::now().naive_utc();
+        for (i, uuid) in column.tasks.iter().enumerate().skip(offset).take(visible) {
+            let Some(task) = tasks.get(uuid) else {
+                continue;
+            };
+            let y = inner.y + (i - offset) as u16 * CARD_HEIGHT;
+            let card = Rect::new(inner.x, y, inner.width, (CARD_HEIGHT - 1).min(inner.bottom() - y));
+            if selected && i == self.card {
+                buf.set_style(card, theme.cursor());
+            }
+            buf.set_line(card.x + 1, card.y, &Line::styled(task.description.clone(), row::task::description_style(task, theme, now)), card.width.saturating_sub(2));
+            let mut details = vec![];
+            if let Some(project) = &task.project {
+                details.push(Span::styled(project.clone(), theme.project()));
+            }
+            if let Some(due) = task.due {
+                if !details.is_empty() {
+                    details.push(Span::styled(format!(" {} ", theme.glyphs().dot), theme.border()));
+                }
+                let style = match task.days_until_due(now) {
+                    Some(days) if days < 0 => theme.overdue(),
+                    Some(0) => theme.due_today(),
+                    _ => theme.marker(),
+                };
+                details.push(Span::styled(due.format("%Y-%m-%d").to_string(), style));
+            }
+            if card.height > 1 {
+                buf.set_line(card.x + 1, card.y + 1, &Line::from(details), card.width.saturating_sub(2));
+            }
+        }
+        (inner, offset)
+    }
+
+}
+
+/// Taskwarrior's `someday`, for tasks that wait until they're moved out again
+fn someday() -> Option<NaiveDateTime> {
+    NaiveDate::from_ymd_opt(9999, 12, 30)?.and_hms_opt(0, 0, 0)
+}
+
+fn column_title(value: &str) -> &str {
+    if value.is_empty() {
+        "None"
+    } else {
+        value
+    }
+}
+
+impl Tab for Kanban {
+
+    fn title(&self) -> String {
+        "Kanban".to_string()
+    }
+
+    fn draw(&mut self, theme: SharedTheme, frame: &mut Frame, area: Rect, tasks: &HashMap<Uuid, Task>) -> Result<()> {
+        if self.columns.is_empty() {
+            let line = Line::styled("No kanban columns configured", theme.text());
+            frame.buffer_mut().set_line(area.x, area.y, &line, area.width);
+            return Ok(());
+        }
+        let count = self.columns.len() as u32;
+        let layout = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints((0..count).map(|_| Constraint::Ratio(1, count)))
+            .split(area);
+        self.areas = layout.iter()
+            .enumerate()
+            .map(|(i, column_area)| self.draw_column(&theme, frame, *column_area, i, tasks))
+            .collect();
+        Ok(())
+    }
+
+    fn event(&mut self, event: Event, tasks: &HashMap<Uuid, Task>) -> Vec<Action> {
+        if let Event::Mouse(m) = event {
+            self.mouse(m);
+        }
+        if let Event::Key(k) = event {
+            match k.code {
+                KeyCode::Char('h') => self.select_column(-1),
+                KeyCode::Char('l') => self.select_column(1),
+                KeyCode::Char('j') => self.select_card(1),
+                KeyCode::Char('k') => self.select_card(-1),
+                KeyCode::Char('H') => return self.move_task(tasks, -1),
+                KeyCode::Char('L') => return self.move_task(tasks, 1),
+                _ => {}
+            }
+        }
+        vec![]
+    }
+
+    fn refresh(&mut self, tasks: &HashMap<Uuid, Task>) {
+        let selected = self.selected();
+        for column in &mut self.columns {
+            column.tasks.clear();
+        }
+        let mut sorted: Vec<&Task> = tasks.values().collect();
+        sorted.sort_by(|a, b| b.urgency.total_cmp(&a.urgency).then_with(|| a.description.cmp(&b.description)));
+        for task in sorted {
+            let Some(value) = self.value(task) else {
+                continue;
+            };
+            if let Some(column) = self.columns.iter_mut().find(|c| c.value == value) {
+                column.tasks.push(task.uuid);
+            }
+        }
+        // Follow the selected task if it moved
+        if let Some(uuid) = selected {
+            for (i, column) in self.columns.iter().enumerate() {
+                if let Some(card) = column.tasks.iter().position(|u| *u == uuid) {
+                    self.column = i;
+                    self.card = card;
+                }
+            }
+        }
+        self.clamp_card();
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::taskrc::TaskRc;
+
+    use super::*;
+
+    fn board(by: &str, columns: &[&str], tasks: &HashMap<Uuid, Task>) -> Kanban {
+        let config = KanbanConfig { by: by.to_string(), columns: columns.iter().map(|c| c.to_string()).collect() };
+        let udas = TaskRc::parse("uda.priority.type=string\nuda.priority.values=H,M,L,\n").udas();
+        Kanban::new(tasks, &config, SharedUdas::new(udas))
+    }
+
+    #[test]
+    fn status_board() {
+        let kanban = board("status", &[], &HashMap::new());
+        let task = Task::new("Plan".to_string());
+        assert_eq!(kanban.value(&task).as_deref(), Some("pending"));
+
+        let waiting = kanban.set_value(&task, "pending", "waiting").unwrap().after;
+        assert_eq!(waiting.status, TaskStatus::Waiting);
+        assert!(waiting.wait.is_some_and(|w| w > Utc::now().naive_utc()));
+        assert_eq!(kanban.value(&waiting).as_deref(), Some("waiting"));
+
+        let pending = kanban.set_value(&waiting, "waiting", "pending").unwrap().after;
+        assert_eq!(pending.status, TaskStatus::Pending);
+        assert_eq!(pending.wait, None);
+
+        let completed = kanban.set_value(&waiting, "waiting", "completed").unwrap().after;
+        assert_eq!(completed.status, TaskStatus::Completed);
+        assert!(completed.end.is_some());
+        assert_eq!(completed.wait, None);
+        let reopened = kanban.set_value(&completed, "completed", "pending").unwrap().after;
+        assert_eq!(reopened.end, None);
+
+        assert!(kanban.set_value(&task, "pending", "nonsense").is_none());
+    }
+
+    #[test]
+    fn tag_board() {
+        let kanban = board("tag", &["", "next", "doing"], &HashMap::new());
+        let mut task = Task::new("Review".to_string());
+        task.tags = vec!["work".to_string()];
+        assert_eq!(kanban.value(&task).as_deref(), Some(""));
+
+        let next = kanban.set_value(&task, "", "next").unwrap().after;
+        assert_eq!(next.tags, vec!["work", "next"]);
+        assert_eq!(kanban.value(&next).as_deref(), Some("next"));
+
+        let doing = kanban.set_value(&next, "next", "doing").unwrap().after;
+        assert_eq!(doing.tags, vec!["work", "doing"]);
+        let untagged = kanban.set_value(&doing, "doing", "").unwrap().after;
+        assert_eq!(untagged.tags, vec!["work"]);
+
+        // Resolved tasks are only on the status board
+        let done = Change::complete(&next).after;
+        assert_eq!(kanban.value(&done), None);
+    }
+
+    #[test]
+    fn uda_board() {
+        let mut task = Task::new("Ship".to_string());
+        let tasks = HashMap::from([(task.uuid, task.clone())]);
+        let kanban = board("priority", &[], &tasks);
+        let columns: Vec<&str> = kanban.columns.iter().map(|c| c.value.as_str()).collect();
+        assert_eq!(columns, vec!["H", "M", "L", ""]);
+        assert_eq!(kanban.columns[3].tasks, vec![task.uuid]);
+
+        task = kanban.set_value(&task, "", "H").unwrap().after;
+        assert_eq!(task.udas.get("priority"), Some(&Value::String("H".to_string())));
+        assert_eq!(kanban.value(&task).as_deref(), Some("H"));
+
+        task = kanban.set_value(&task, "H", "").unwrap().after;
+        assert!(!task.udas.contains_key("priority"));
+        assert_eq!(kanban.value(&task).as_deref(), Some(""));
+    }
+}
@@ -1,54 +1,120 @@
+use chrono::{Local, NaiveDate, NaiveDateTime, Utc};
 use std::{cmp::Ordering, collections::{HashMap, HashSet, VecDeque}};
 use color_eyre::Result;
 
-use crossterm::event::KeyCode;
-use ratatui::{layout::Rect, Frame};
+use crossterm::event::{KeyCode, MouseButton, MouseEvent, MouseEventKind};
+use ratatui::{layout::{Constraint, Direction, Layout, Rect}, Frame};
 use strum::IntoEnumIterator;
 use uuid::Uuid;
 
-use crate::{data::{Task, TaskStatus}, event::Event, ui::{row::{task::TaskRow, text::TextRow, FoldState, RowEntry}, style::SharedTheme, tasklist::TaskListWidget}, util::{self, graph::{Idable, Node}}};
+use crate::{action::{self, Action}, backend::Change, config::SharedConfig, tabs::Tab, data::{self, Annotation, Task, TaskStatus}, event::Event, taskrc::{SharedColors, SharedUdas, UdaDefinition}, timew::SharedTracked, ui::{detail::DetailWidget, prompt::Prompt, row::{annotation::AnnotationRow, task::{Progress, TaskRow}, text::TextRow, FoldState, RowEntry}, style::SharedTheme, tasklist::{RowArea, TableColumn, TaskListWidget}}, util::{self, graph::{Dependencies, DependsOn, Idable, Node, ParentToChild}}};
+
+/// Rows moved by a turn of the mouse wheel
+const SCROLL: isize = 3;
 
 
 
 #[derive(Debug, Clone)]
 pub struct List {
+    tree: Tree,
     rows: Vec<RowEntry>,
     pub cursor: usize,
     pub focus: usize,
     last_size: Option<Rect>,
+    /// Width of the description column when last drawn, rows wrap to it
+    description_width: u16,
+    /// Theme when last drawn, rows are measured with its glyphs
+    pub theme: SharedTheme,
+    /// Rows on screen when last drawn
+    layout: Vec<RowArea>,
     folded: HashSet<usize>,
+    columns: Vec<TableColumn>,
+    sort: Option<UdaDefinition>,
+    udas: SharedUdas,
+    /// Taskwarrior's color rules, when they're used instead of the theme
+    pub colors: Option<SharedColors>,
+    pub config: SharedConfig,
+    hierarchy: Hierarchy,
+    visibility: Visibility,
+    /// Waiting for the status to toggle after `f`
+    filtering: bool,
+    separation: Separation,
+    /// What the list goes back to from the levels view
+    base: Separation,
+    pub dependencies: Dependencies,
+    goal: Option<Uuid>,
+    pub critical_path: HashSet<Uuid>,
+    /// Time active tasks are timed against, updated every tick
+    pub now: NaiveDateTime,
+    /// Empty unless Timewarrior is enabled
+    pub tracked: SharedTracked,
+    details: bool,
 }
 
 impl List {
 
-    pub fn new(tasks: &HashMap<Uuid, Task>) -> Self {
+    pub fn new(tasks: &HashMap<Uuid, Task>, config: SharedConfig, udas: SharedUdas, colors: Option<SharedColors>, tracked: SharedTracked) -> Self {
+        List::with_separation(tasks, config, udas, colors, tracked, Separation::Status)
+    }
+
+    /// Tasks by the day they're due or scheduled, with started ones at the top
+    pub fn agenda(tasks: &HashMap<Uuid, Task>, config: SharedConfig, udas: SharedUdas, colors: Option<SharedColors>, tracked: SharedTracked) -> Self {
+        let days = config.agenda_days;
+        List::with_separation(tasks, config, udas, colors, tracked, Separation::Agenda { days })
+    }
+
+    fn with_separation(tasks: &HashMap<Uuid, Task>, config: SharedConfig, udas: SharedUdas, colors: Option<SharedColors>, tracked: SharedTracked, separation: Separation) -> Self {
         let hashset = HashSet::new();
-        let rows = get_tasks(tasks, Separation::Status, &hashset);
+        let sort = config.sort.as_ref().map(|name| {
+            udas.get(name).cloned().unwrap_or_else(|| UdaDefinition::new(name.clone()))
+        });
+        let hierarchy = Hierarchy::SubOf;
+        let visibility = Visibility {
+            statuses: config.statuses.iter().copied().collect(),
+            hide_templates: config.hide_templates,
+        };
+        let tree = Tree::new(tasks, separation, hierarchy, sort.as_ref(), &visibility);
+        let rows = tree.rows(&hashset);
+        let now = Utc::now().naive_utc();
         List {
+            tree,
             rows,
             cursor: 0,
             focus: 0,
             folded: hashset,
             last_size: None,
+            description_width: u16::MAX,
+            theme: SharedTheme::default(),
+            layout: vec![],
+            columns: config.columns.iter().map(|c| TableColumn::from_name(c)).collect(),
+            sort,
+            udas,
+            colors,
+            config,
+            hierarchy,
+            visibility,
+            filtering: false,
+            separation,
+            base: separation,
+            dependencies: Dependencies::new(tasks),
+            goal: None,
+            critical_path: HashSet::new(),
+            now,
+            tracked,
+            details: false,
         }
     }
 
-    pub fn draw(&mut self, theme: SharedTheme, frame: &mut Frame, area: Rect, task_map: &HashMap<Uuid, Task>) -> Result<()> {
-        let list_component = TaskListWidget::new(&self.rows, theme);
-        self.last_size = Some(area);
-        list_component.render(area, frame.buffer_mut(), self, task_map);
-        Ok(())
-    }
-
     pub fn is_folded(&self, index: usize) -> bool {
         self.folded.contains(&index)
     }
 
-    fn focus(&mut self) {
+    fn focus(&mut self, tasks: &HashMap<Uuid, Task>) {
         if let Some(area) = self.last_size {
             let height = area.height;
             let last_focus = self.focus;
-            self.focus = List::keep_focus(height.into(), 7, self.cursor, last_focus, self.rows.len());
+            let row_height = |i: usize| self.rows.get(i).map_or(1, |r| r.height(self, tasks, self.description_width));
+            self.focus = List::keep_focus(height.into(), 7, self.cursor, last_focus, self.rows.len(), row_height);
         }
     }
 
@@ -71,31 +137,57 @@ impl List {
         }
     }
 
-    fn keep_focus(height: usize, padding: usize, cursor: usize, previous_focus: usize, max_len: usize) -> usize {
+    /// First row to draw so that `padding` rows stay visible around the cursor. Rows can take
+    /// up more than one line, `row_height` gives how many.
+    fn keep_focus(height: usize, padding: usize, cursor: usize, previous_focus: usize, max_len: usize, row_height: impl Fn(usize) -> usize) -> usize {
         // Lower bound
         if cursor <= padding {
             return 0
         }
-        // Add done to upper to make it symmetrical
-        let padding_lower = padding;
-        let padding_upper = padding + 1;
-
-        let previous_high = previous_focus + height;
-        if previous_high - padding_upper >= cursor && previous_focus + padding_lower <= cursor {
+        let bottom = cursor + padding;
+        if previous_focus + padding <= cursor && (previous_focus..=bottom).map(&row_height).sum::<usize>() <= height {
             // We're still in focus
             return previous_focus
         }
-        if cursor <= previous_focus + padding_lower {
-            // Moving down
+        if cursor < previous_focus + padding {
+            // Moving up
             return cursor - padding
-
         }
-        let target = cursor + padding_upper - height;
-        if target > max_len {
-            max_len
-        } else {
-            target
+        // Fit as many rows as possible above the padding under the cursor
+        let mut target = bottom + 1;
+        let mut used = 0;
+        while target > 0 {
+            let row = row_height(target - 1);
+            if used + row > height {
+                break;
+            }
+            used += row;
+            target -= 1;
         }
+        target.min(cursor).min(max_len)
+    }
+
+    /// Clicking selects a row, or folds it when it's on the fold glyph, and the wheel scrolls
+    fn mouse(&mut self, event: MouseEvent, tasks: &HashMap<Uuid, Task>) {
+        match event.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                let position = (event.column, event.row);
+                let Some(row) = self.layout.iter().find(|r| {
+                    let area = r.area;
+                    (area.left()..area.right()).contains(&position.0) && (area.top()..area.bottom()).contains(&position.1)
+                }).copied() else {
+                    return;
+                };
+                self.cursor = row.index;
+                if row.fold == Some(position) {
+                    self.fold_row(row.index);
+                }
+            },
+            MouseEventKind::ScrollDown => self.cursor(SCROLL),
+            MouseEventKind::ScrollUp => self.cursor(-SCROLL),
+            _ => return,
+        }
+        self.focus(tasks);
     }
 
     fn move_backward(&self, cursor: usize, change: usize) -> usize {
@@ -108,13 +200,13 @@ impl List {
 
     fn move_forward(&self, cursor: usize, change: usize) -> usize {
         if cursor + change >= self.rows.len() {
-            self.rows.len() - 1
+            self.rows.len().saturating_sub(1)
         } else {
             cursor + change
         }
     }
 
-    fn fold_row(&mut self, index: usize, tasks: &HashMap<Uuid, Task>) {
+    fn fold_row(&mut self, index: usize) {
         if let Some(row) = self.rows.get(index) {
             if row.fold_state() == FoldState::NoChildren {
                 return
@@ -122,45 +214,268 @@ impl List {
             if !self.folded.remove(&row.index()) {
                 self.folded.insert(row.index());
             }
-            self.rows = get_tasks(tasks, Separation::Status, &self.folded);
+            self.rows = self.tree.rows(&self.folded);
+        }
+    }
+
+    fn toggle_hierarchy(&mut self, tasks: &HashMap<Uuid, Task>) {
+        self.hierarchy = match self.hierarchy {
+            Hierarchy::SubOf => Hierarchy::Depends,
+            Hierarchy::Depends => Hierarchy::SubOf,
+        };
+        self.rebuild(tasks);
+    }
+
+    fn toggle_levels(&mut self, tasks: &HashMap<Uuid, Task>) {
+        self.separation = match self.separation {
+            Separation::Levels => self.base,
+            _ => Separation::Levels,
+        };
+        self.rebuild(tasks);
+    }
+
+    /// Shows or hides a status
+    fn toggle_status(&mut self, status: TaskStatus, tasks: &HashMap<Uuid, Task>) {
+        if !self.visibility.statuses.remove(&status) {
+            self.visibility.statuses.insert(status);
+        }
+        self.rebuild(tasks);
+    }
+
+    fn rebuild(&mut self, tasks: &HashMap<Uuid, Task>) {
+        // Fold indices don't carry over to a different tree
+        self.folded.clear();
+        self.cursor = 0;
+        self.focus = 0;
+        self.build(tasks);
+    }
+
+    /// Arranges the tasks again, keeping the folds
+    fn build(&mut self, tasks: &HashMap<Uuid, Task>) {
+        self.tree = Tree::new(tasks, self.separation, self.hierarchy, self.sort.as_ref(), &self.visibility);
+        self.rows = self.tree.rows(&self.folded);
+    }
+
+    pub fn selected_task(&self) -> Option<Uuid> {
+        match self.rows.get(self.cursor)? {
+            RowEntry::Task(t) => Some(t.task),
+            _ => None,
         }
     }
 
-    fn complete(&self) {
+    /// The task of the selected row, or the task an annotation belongs to
+    fn selected_owner(&self) -> Option<Uuid> {
+        match self.rows.get(self.cursor)? {
+            RowEntry::Task(t) => Some(t.task),
+            RowEntry::Annotation(a) => Some(a.task),
+            _ => None,
+        }
+    }
 
+    fn annotate(&self, tasks: &HashMap<Uuid, Task>) -> Vec<Action> {
+        let Some(task) = self.selected_owner().and_then(|u| tasks.get(&u)).cloned() else {
+            return vec![];
+        };
+        vec![Action::Prompt(Prompt::input(
+            "Annotate",
+            format!("Annotation for \"{}\"", task.description),
+            move |text| vec![Action::Apply(vec![Change::modify(&task, |t| t.annotations.push(Annotation::new(text.to_string())))])],
+        ))]
+    }
+
+    fn denotate(&self, tasks: &HashMap<Uuid, Task>) -> Vec<Action> {
+        let Some(RowEntry::Annotation(row)) = self.rows.get(self.cursor) else {
+            return vec![];
+        };
+        let Some(task) = tasks.get(&row.task) else {
+            return vec![];
+        };
+        let Some(annotation) = task.annotations.get(row.annotation) else {
+            return vec![];
+        };
+        let change = Change::modify(task, |t| {
+            t.annotations.remove(row.annotation);
+        });
+        vec![Action::Prompt(Prompt::confirm(
+            "Remove annotation",
+            format!("Remove \"{}\" from \"{}\"?", annotation.description, task.description),
+            vec![Action::Apply(vec![change])],
+        ))]
+    }
+
+    /// Highlights the longest chain of dependencies leading to the selected task.
+    /// Selecting the current goal again clears it.
+    fn toggle_goal(&mut self, tasks: &HashMap<Uuid, Task>) {
+        let selected = self.selected_task();
+        self.goal = if selected == self.goal { None } else { selected };
+        self.update_critical_path(tasks);
+    }
+
+    fn update_critical_path(&mut self, tasks: &HashMap<Uuid, Task>) {
+        self.critical_path = match self.goal {
+            Some(goal) => util::graph::critical_path(tasks, &goal).into_iter().collect(),
+            None => HashSet::new(),
+        };
+    }
+
+    fn complete(&self, tasks: &HashMap<Uuid, Task>) -> Vec<Action> {
+        match self.selected_task().and_then(|u| tasks.get(&u)) {
+            Some(task) => action::complete(tasks, task, self.config.complete_parents),
+            None => vec![],
+        }
+    }
+
+    fn delete(&self, tasks: &HashMap<Uuid, Task>) -> Vec<Action> {
+        match self.selected_task().and_then(|u| tasks.get(&u)) {
+            Some(task) => action::delete(tasks, task),
+            None => vec![],
+        }
+    }
+
+    fn toggle_start(&self, tasks: &HashMap<Uuid, Task>) -> Vec<Action> {
+        match self.selected_task().and_then(|u| tasks.get(&u)) {
+            Some(task) if !task.is_resolved() => vec![Action::Apply(vec![Change::modify(task, Task::toggle_start)])],
+            _ => vec![],
+        }
+    }
+
+}
+
+
+impl Tab for List {
+
+    fn title(&self) -> String {
+        match self.base {
+            Separation::Agenda { .. } => "Agenda".to_string(),
+            _ => "List".to_string(),
+        }
+    }
+
+    fn draw(&mut self, theme: SharedTheme, frame: &mut Frame, area: Rect, task_map: &HashMap<Uuid, Task>) -> Result<()> {
+        let mut area = area;
+        if self.details {
+            let layout = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Fill(1), Constraint::Length(40)])
+                .split(area);
+            area = layout[0];
+            if let Some(task) = self.selected_task().and_then(|u| task_map.get(&u)) {
+                DetailWidget::new(task, &self.tracked.borrow(), self.now, theme.clone()).render(layout[1], frame.buffer_mut());
+            }
+        }
+        self.theme = theme.clone();
+        let list_component = TaskListWidget::new(&self.rows, theme, self.udas.clone())
+            .columns(self.columns.clone());
+        self.last_size = Some(area);
+        self.description_width = list_component.description_width(area.width);
+        self.layout = list_component.render(area, frame.buffer_mut(), self, task_map);
+        Ok(())
     }
 
-    pub fn event(&mut self, event: Event, tasks: &HashMap<Uuid, Task>) {
+    fn event(&mut self, event: Event, tasks: &HashMap<Uuid, Task>) -> Vec<Action> {
+        if let Event::Mouse(m) = event {
+            self.mouse(m, tasks);
+            return vec![];
+        }
         if let Event::Key(k) = event {
+            if self.filtering {
+                self.filtering = false;
+                let status = match k.code {
+                    KeyCode::Char('p') => TaskStatus::Pending,
+                    KeyCode::Char('b') => TaskStatus::Blocked,
+                    KeyCode::Char('w') => TaskStatus::Waiting,
+                    KeyCode::Char('c') => TaskStatus::Completed,
+                    KeyCode::Char('d') => TaskStatus::Deleted,
+                    KeyCode::Char('r') => TaskStatus::Recurring,
+                    _ => return vec![],
+                };
+                self.toggle_status(status, tasks);
+                return vec![];
+            }
             match k.code {
+                KeyCode::Char('f') => {
+                    self.filtering = true;
+                }
                 KeyCode::Char('j') => {
                     self.cursor(1);
-                    self.focus();
+                    self.focus(tasks);
                 }
                 KeyCode::Char('k') => {
                     self.cursor(-1);
-                    self.focus();
+                    self.focus(tasks);
                 }
                 KeyCode::Char('c') => {
-                    self.complete();
+                    return self.complete(tasks);
+                }
+                KeyCode::Char('d') => {
+                    return self.delete(tasks);
+                }
+                KeyCode::Char('s') => {
+                    return self.toggle_start(tasks);
                 }
                 KeyCode::Enter => {
-                    self.fold_row(self.cursor, tasks);
+                    self.fold_row(self.cursor);
+                }
+                KeyCode::Char('v') => {
+                    self.toggle_hierarchy(tasks);
+                }
+                KeyCode::Char('n') => {
+                    self.toggle_levels(tasks);
+                }
+                KeyCode::Char('g') => {
+                    self.toggle_goal(tasks);
+                }
+                KeyCode::Char('i') => {
+                    self.details = !self.details;
+                }
+                KeyCode::Char('a') => {
+                    return self.annotate(tasks);
+                }
+                KeyCode::Char('x') => {
+                    return self.denotate(tasks);
                 }
                 _ => {}
             }
         }
+        vec![]
     }
 
-}
+    /// Keeps folds and the cursor where they were
+    fn refresh(&mut self, tasks: &HashMap<Uuid, Task>) {
+        self.dependencies = Dependencies::new(tasks);
+        self.update_critical_path(tasks);
+        self.build(tasks);
+        self.cursor = self.cursor.min(self.rows.len().saturating_sub(1));
+        self.focus(tasks);
+    }
 
+    fn tick(&mut self) {
+        self.now = Utc::now().naive_utc();
+    }
 
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Separation {
     None,
     Status,
+    /// What can be done next, ordered by dependencies
+    Levels,
+    /// Started, overdue, today, tomorrow, and then each of the next `days`
+    Agenda { days: u32 },
+}
+
+/// What makes a task the child of another
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hierarchy {
+    /// Subtasks through the `sub_of` UDA
+    SubOf,
+    /// Blocking chains through `depends`, blockers are children of the tasks they block
+    Depends,
 }
 
-fn node_to_row(node: &Node, idx: usize, depth: usize, fold_state: FoldState) -> RowEntry {
+fn node_to_row(node: &Node, idx: usize, last: Vec<bool>, fold_state: FoldState, progress: &HashMap<Uuid, Progress>) -> RowEntry {
+    let depth = last.len();
     match node {
         Node::Text(ref t) => {
             RowEntry::Text(
@@ -172,105 +487,384 @@ fn node_to_row(node: &Node, idx: usize, depth: usize, fold_state: FoldState) ->
                 }
             )
         }
+        Node::Annotation(ref a) => {
+            RowEntry::Annotation(
+                AnnotationRow {
+                    task: a.val,
+                    annotation: a.index,
+                    depth,
+                    last,
+                    idx,
+                }
+            )
+        }
         Node::Task(ref t) => {
             RowEntry::Task(
                 TaskRow { 
                     task: t.val, 
                     depth, 
+                    last,
                     fold_state,
                     idx,
+                    progress: progress.get(&t.val).copied(),
                 }
             )
         }
     }
 }
 
-pub fn get_tasks(tasks: &HashMap<Uuid, Task>, separation: Separation, folded: &HashSet<usize>) -> Vec<RowEntry> {
-    let mut nodes = util::graph::graph_nodes(tasks);
+/// Completed out of all the tasks under a task, including hidden ones. Deleted tasks don't count.
+fn subtree_progress(uuid: &Uuid, tasks: &HashMap<Uuid, Task>, children: &HashMap<Uuid, Vec<Uuid>>) -> Option<Progress> {
+    let mut progress = Progress::default();
+    let mut seen: HashSet<Uuid> = HashSet::from([*uuid]);
+    let mut stack: Vec<&Uuid> = children.get(uuid).map(|c| c.iter().collect()).unwrap_or_default();
+    while let Some(child) = stack.pop() {
+        if !seen.insert(*child) {
+            continue;
+        }
+        match tasks.get(child).map(|t| t.status) {
+            Some(TaskStatus::Deleted) | None => {},
+            Some(TaskStatus::Completed) => {
+                progress.done += 1;
+                progress.total += 1;
+            },
+            Some(_) => progress.total += 1,
+        }
+        stack.extend(children.get(child).into_iter().flatten());
+    }
+    if progress.total == 0 {
+        None
+    } else {
+        Some(progress)
+    }
+}
 
-    // Reverse because we go back to front
+/// A task as it's placed in the tree. Recurring instances go under their template, and tasks
+/// whose parent isn't shown become roots.
+#[derive(Debug)]
+struct Placed<'a> {
+    task: &'a Task,
+    parent: Option<Uuid>,
+}
 
-    let mut separation_nodes: Vec<Node> = match separation {
-        Separation::Status => {
-            let mut status_map: HashMap<TaskStatus, Vec<Node>> = TaskStatus::iter().map(|s| (s, vec![])).collect();
-            for node in nodes.into_iter() {
-                status_map.get_mut(&tasks.get(node.get_id_ref()).unwrap().status).unwrap().push(node);
-            };
-            let sorted_status = {
-                let mut status_vec: Vec<_> = TaskStatus::iter().collect();
-                status_vec.sort();
-                status_vec
-            };
-            let mut new_nodes = Vec::new();
+impl ParentToChild for Placed<'_> {
 
-            for status in sorted_status {
-                let mut inner_nodes = status_map.remove(&status).unwrap();
-                if inner_nodes.is_empty() {
-                    continue;
-                }
-                sort_tasks(&mut inner_nodes, tasks);
-                inner_nodes.reverse();
-                new_nodes.push(Node::text(status.to_string(), inner_nodes))
-            }
+    fn sub_of(&self) -> Option<Uuid> {
+        self.parent
+    }
+
+    fn get_id(&self) -> Uuid {
+        self.task.uuid
+    }
+
+    fn get_id_ref(&self) -> &Uuid {
+        &self.task.uuid
+    }
 
-            new_nodes
+}
+
+impl DependsOn for Placed<'_> {
+
+    fn depends(&self) -> &[Uuid] {
+        &self.task.depends
+    }
+
+    fn is_resolved(&self) -> bool {
+        self.task.is_resolved()
+    }
+
+}
 
+/// Which tasks are in the list
+#[derive(Debug, Clone)]
+pub struct Visibility {
+    pub statuses: HashSet<TaskStatus>,
+    /// Templates are left out even when they'd connect their instances
+    pub hide_templates: bool,
+}
+
+impl Visibility {
+
+    fn shows(&self, task: &Task) -> bool {
+        self.statuses.contains(&task.status) && self.allows(task)
+    }
+
+    /// Whether a task can be shown to connect the tasks under it
+    fn allows(&self, task: &Task) -> bool {
+        !self.hide_templates || task.status != TaskStatus::Recurring
+    }
+
+}
+
+/// The parent of a task in the tree: a recurring instance is under its template, and a subtask
+/// under its `sub_of`. Instances copy `sub_of` from their template, so the template goes first.
+fn tree_parent(task: &Task) -> Option<Uuid> {
+    task.parent.or(task.sub_of)
+}
+
+/// Like `tree_parent`, but an instance of a hidden template goes under the template's `sub_of`
+fn allowed_parent<'a>(task: &Task, tasks: &'a HashMap<Uuid, Task>, visibility: &Visibility) -> Option<&'a Task> {
+    [task.parent, task.sub_of].into_iter()
+        .flatten()
+        .filter_map(|p| tasks.get(&p))
+        .find(|p| visibility.allows(p))
+}
+
+/// Shown tasks, along with the hidden ancestors that connect them to the tree
+fn place<'a>(tasks: &'a HashMap<Uuid, Task>, visibility: &Visibility) -> HashMap<Uuid, Placed<'a>> {
+    let mut kept: HashSet<Uuid> = HashSet::new();
+    for task in tasks.values().filter(|t| visibility.shows(t)) {
+        let mut current = Some(task);
+        while let Some(t) = current {
+            if !kept.insert(t.uuid) {
+                break;
+            }
+            current = allowed_parent(t, tasks, visibility);
         }
-        Separation::None => {
-            sort_tasks(&mut nodes, tasks);
-            nodes
+    }
+    kept.iter()
+        .map(|uuid| {
+            let task = &tasks[uuid];
+            let parent = allowed_parent(task, tasks, visibility).map(|p| p.uuid).filter(|p| kept.contains(p));
+            (*uuid, Placed { task, parent })
+        })
+        .collect()
+}
+
+/// Tasks arranged into groups and subtrees. Building this is the slow part, so it's only done
+/// when the tasks or the arrangement change, while folding just flattens it into rows again.
+#[derive(Debug, Clone, Default)]
+pub struct Tree {
+    /// Back to front, since rows are taken off the back
+    nodes: Vec<Node>,
+    progress: HashMap<Uuid, Progress>,
+}
+
+impl Tree {
+
+    pub fn new(tasks: &HashMap<Uuid, Task>, separation: Separation, hierarchy: Hierarchy, sort: Option<&UdaDefinition>, visibility: &Visibility) -> Tree {
+        let placed = place(tasks, visibility);
+        // Progress counts subtasks, shown or not, and blockers never count even when the
+        // tree is built from them
+        let mut children: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
+        for task in tasks.values() {
+            if let Some(parent) = tree_parent(task) {
+                children.entry(parent).or_default().push(task.uuid);
+            }
         }
-    };
+        let nodes = || match hierarchy {
+            Hierarchy::SubOf => util::graph::graph_nodes(&placed),
+            Hierarchy::Depends => util::graph::dependency_nodes(&placed),
+        };
 
-    separation_nodes.reverse();
+        // Reverse because we go back to front
 
-    let mut traverse: VecDeque<_> = separation_nodes.into_iter().collect();
-    let mut depth: VecDeque<usize> = VecDeque::new();
-    let mut rows = Vec::new();
-    let mut idx = 0;
+        let mut separation_nodes: Vec<Node> = match separation {
+            Separation::Status => {
+                let mut status_map: HashMap<TaskStatus, Vec<Node>> = TaskStatus::iter().map(|s| (s, vec![])).collect();
+                for node in nodes().into_iter() {
+                    status_map.get_mut(&tasks.get(node.get_id_ref()).unwrap().status).unwrap().push(node);
+                };
+                let sorted_status = {
+                    let mut status_vec: Vec<_> = TaskStatus::iter().collect();
+                    status_vec.sort();
+                    status_vec
+                };
+                let mut new_nodes = Vec::new();
 
-    while let Some(node) = traverse.pop_back() {
-        let fold_state = if node.child_len() == 0 {
-            FoldState::NoChildren
-        } else if folded.contains(&idx) {
-            FoldState::Folded
-        } else {
-            FoldState::Open
+                for status in sorted_status {
+                    let mut inner_nodes = status_map.remove(&status).unwrap();
+                    if inner_nodes.is_empty() {
+                        continue;
+                    }
+                    sort_tasks(&mut inner_nodes, tasks, sort);
+                    inner_nodes.reverse();
+                    new_nodes.push(Node::text(status.to_string(), inner_nodes))
+                }
+
+                new_nodes
+
+            }
+            Separation::Levels => {
+                // This is an order, so the hierarchy doesn't apply
+                let levels = util::graph::dependency_levels(&placed);
+                let mut levelled: HashSet<Uuid> = HashSet::new();
+                let mut new_nodes = Vec::new();
+
+                for (i, level) in levels.into_iter().enumerate() {
+                    levelled.extend(level.iter());
+                    let mut inner_nodes: Vec<Node> = level.into_iter().map(|u| Node::task(u, vec![])).collect();
+                    sort_tasks(&mut inner_nodes, tasks, sort);
+                    inner_nodes.reverse();
+                    let title = if i == 0 { "Ready".to_string() } else { format!("After {}", i) };
+                    new_nodes.push(Node::text(title, inner_nodes));
+                }
+
+                let mut stuck: Vec<Node> = placed.values()
+                    .map(|p| p.task)
+                    .filter(|t| !t.is_resolved() && !levelled.contains(&t.uuid))
+                    .map(|t| Node::task(t.uuid, vec![]))
+                    .collect();
+                if !stuck.is_empty() {
+                    sort_tasks(&mut stuck, tasks, sort);
+                    stuck.reverse();
+                    new_nodes.push(Node::text("Blocked by a cycle".to_string(), stuck));
+                }
+
+                new_nodes
+            }
+            Separation::Agenda { days } => {
+                let today = Local::now().date_naive();
+                let mut sections: Vec<Vec<Node>> = (0..days + 4).map(|_| vec![]).collect();
+                for task in placed.values().map(|p| p.task) {
+                    if let Some(section) = agenda_section(task, today, days) {
+                        sections[section].push(Node::task(task.uuid, vec![]));
+                    }
+                }
+
+                let mut new_nodes = Vec::new();
+                for (i, mut inner_nodes) in sections.into_iter().enumerate() {
+                    if inner_nodes.is_empty() {
+                        continue;
+                    }
+                    sort_tasks(&mut inner_nodes, tasks, sort);
+                    inner_nodes.reverse();
+                    let title = match i {
+                        0 => "Active".to_string(),
+                        1 => "Overdue".to_string(),
+                        2 => "Today".to_string(),
+                        3 => "Tomorrow".to_string(),
+                        i => (today + chrono::Duration::days(i as i64 - 2)).format("%A %Y-%m-%d").to_string(),
+                    };
+                    new_nodes.push(Node::text(title, inner_nodes));
+                }
+
+                new_nodes
+            }
+            Separation::None => {
+                let mut nodes = nodes();
+                sort_tasks(&mut nodes, tasks, sort);
+                nodes
+            }
         };
-        let row = node_to_row(&node, idx, depth.len(), fold_state.clone());
-        rows.push(row);
-        let d_opt = depth.pop_back().map(|v| if v > 0 { v - 1 } else { v });
-        if !node.sub().is_empty() && fold_state != FoldState::Folded {
-            // Children
-            if let Some(d) = d_opt {
-                depth.push_back(d);
+
+        attach_annotations(&mut separation_nodes, tasks);
+        separation_nodes.reverse();
+
+        let progress = placed.keys()
+            .filter_map(|uuid| subtree_progress(uuid, tasks, &children).map(|p| (*uuid, p)))
+            .collect();
+
+        Tree {
+            nodes: separation_nodes,
+            progress,
+        }
+    }
+
+    /// Rows that are visible with the given folds
+    pub fn rows(&self, folded: &HashSet<usize>) -> Vec<RowEntry> {
+        let mut traverse: Vec<&Node> = self.nodes.iter().collect();
+        let mut depth: VecDeque<usize> = VecDeque::new();
+        let mut rows = Vec::new();
+        let mut idx = 0;
+
+        while let Some(node) = traverse.pop() {
+            let fold_state = if node.child_len() == 0 {
+                FoldState::NoChildren
+            } else if folded.contains(&idx) {
+                FoldState::Folded
+            } else {
+                FoldState::Open
+            };
+            // Siblings left at each level, the back one still counting this node
+            let mut last: Vec<bool> = depth.iter().map(|d| *d == 0).collect();
+            if let Some(own) = last.last_mut() {
+                *own = depth.back() == Some(&1);
             }
-            depth.push_back(node.sub().len());
-            traverse.extend(node.into_sub());
-            idx += 1;
-        } else {
-            // No Children/folded
-            if let Some(d) = d_opt {
-                if d > 0 {
+            let row = node_to_row(node, idx, last, fold_state.clone(), &self.progress);
+            rows.push(row);
+            let d_opt = depth.pop_back().map(|v| if v > 0 { v - 1 } else { v });
+            if !node.sub().is_empty() && fold_state != FoldState::Folded {
+                // Children
+                if let Some(d) = d_opt {
                     depth.push_back(d);
                 }
+                depth.push_back(node.sub().len());
+                traverse.extend(node.sub());
+                idx += 1;
+            } else {
+                // No Children/folded
+                if let Some(d) = d_opt {
+                    if d > 0 {
+                        depth.push_back(d);
+                    }
+                }
+                while depth.back().map_or_else(|| { false }, |d| {*d == 0}) {
+                    depth.pop_back();
+                }
+                idx += node.recursive_child_len();
             }
-            while depth.back().map_or_else(|| { false }, |d| {*d == 0}) {
-                depth.pop_back();
-            }
-            idx += node.recursive_child_len();
         }
+
+        rows
     }
 
-    rows
 }
 
 
-pub fn sort_tasks(rows: &mut [Node], tasks: &HashMap<Uuid, Task>) {
+/// Annotations go before the subtasks of a task
+fn attach_annotations(nodes: &mut [Node], tasks: &HashMap<Uuid, Task>) {
+    let mut stack: Vec<&mut Node> = nodes.iter_mut().collect();
+    while let Some(node) = stack.pop() {
+        match node {
+            Node::Task(t) => {
+                if let Some(task) = tasks.get(&t.val) {
+                    // Children are shown back to front
+                    t.sub.extend((0..task.annotations.len()).rev().map(|i| Node::annotation(t.val, i)));
+                }
+                stack.extend(t.sub.iter_mut());
+            },
+            Node::Text(t) => stack.extend(t.sub.iter_mut()),
+            Node::Annotation(_) => {},
+        }
+    }
+}
+
+/// Agenda section of a task: 0 is started, 1 is overdue, 2 is today and after that it's days
+/// from today. Tasks use their due date, or their scheduled date if they aren't due.
+fn agenda_section(task: &Task, today: NaiveDate, days: u32) -> Option<usize> {
+    // Templates are never done themselves, their instances are
+    if task.is_resolved() || task.status == TaskStatus::Recurring {
+        return None;
+    }
+    if task.start.is_some() {
+        return Some(0);
+    }
+    let date = data::to_local(&task.due.or(task.scheduled)?).date();
+    let offset = (date - today).num_days();
+    if offset < 0 {
+        Some(1)
+    } else if offset <= days as i64 + 1 {
+        Some(offset as usize + 2)
+    } else {
+        None
+    }
+}
+
+pub fn sort_tasks(rows: &mut [Node], tasks: &HashMap<Uuid, Task>, sort: Option<&UdaDefinition>) {
     rows.sort_by(|a, b| {
         let a = tasks.get(a.get_id_ref()).unwrap();
         let b = tasks.get(b.get_id_ref()).unwrap();
         if a.status == b.status {
+            if let Some(uda) = sort {
+                let a_val = a.udas.get(&uda.name).and_then(|v| uda.parse(v));
+                let b_val = b.udas.get(&uda.name).and_then(|v| uda.parse(v));
+                // Rows get reversed after sorting
+                let cmp = uda.compare(b_val.as_ref(), a_val.as_ref());
+                if cmp != Ordering::Equal {
+                    return cmp;
+                }
+            }
             let cmp = b.urgency.partial_cmp(&a.urgency).expect("Invalid urgency");
             return match cmp {
                 Ordering::Equal => {
@@ -286,6 +880,8 @@ pub fn sort_tasks(rows: &mut [Node], tasks: &HashMap<Uuid, Task>) {
 
 #[cfg(test)]
 mod tests {
+    use chrono::{Datelike, TimeZone};
+
     use super::*;
 
     #[test]
@@ -294,11 +890,11 @@ mod tests {
         let padding = 5;
         let max = 60;
         //                                        cursor/previous
-        assert_eq!(List::keep_focus(height, padding, 0, 0, max), 0);
-        assert_eq!(List::keep_focus(height, padding, 30, 0, max), 6);
-        assert_eq!(List::keep_focus(height, padding, 31, 0, max), 7);
-        assert_eq!(List::keep_focus(height, padding, 41, 0, max), 17);
-        assert_eq!(List::keep_focus(height, padding, 41, 30, max), 30);
+        assert_eq!(List::keep_focus(height, padding, 0, 0, max, |_| 1), 0);
+        assert_eq!(List::keep_focus(height, padding, 30, 0, max, |_| 1), 6);
+        assert_eq!(List::keep_focus(height, padding, 31, 0, max, |_| 1), 7);
+        assert_eq!(List::keep_focus(height, padding, 41, 0, max, |_| 1), 17);
+        assert_eq!(List::keep_focus(height, padding, 41, 30, max, |_| 1), 30);
     }
 
     #[test]
@@ -307,12 +903,370 @@ mod tests {
         let padding = 5;
         let max = 60;
         //                                        cursor/previous
-        assert_eq!(List::keep_focus(height, padding, 0, 30, max), 0);
-        assert_eq!(List::keep_focus(height, padding, 0, 1, max), 0);
-        assert_eq!(List::keep_focus(height, padding, 25, 30, max), 20);
-        assert_eq!(List::keep_focus(height, padding, 25, 26, max), 20);
-        assert_eq!(List::keep_focus(height, padding, 25, 40, max), 20);
-        assert_eq!(List::keep_focus(height, padding, 25, 24, max), 20);
+        assert_eq!(List::keep_focus(height, padding, 0, 30, max, |_| 1), 0);
+        assert_eq!(List::keep_focus(height, padding, 0, 1, max, |_| 1), 0);
+        assert_eq!(List::keep_focus(height, padding, 25, 30, max, |_| 1), 20);
+        assert_eq!(List::keep_focus(height, padding, 25, 26, max, |_| 1), 20);
+        assert_eq!(List::keep_focus(height, padding, 25, 40, max, |_| 1), 20);
+        assert_eq!(List::keep_focus(height, padding, 25, 24, max, |_| 1), 20);
+    }
+
+    #[test]
+    fn keep_focus_tall_rows() {
+        let height = 30;
+        let padding = 5;
+        let max = 60;
+        // Every row is three lines, so only ten fit
+        let tall = |_| 3;
+        assert_eq!(List::keep_focus(height, padding, 4, 0, max, tall), 0);
+        assert_eq!(List::keep_focus(height, padding, 6, 0, max, tall), 2);
+        assert_eq!(List::keep_focus(height, padding, 7, 2, max, tall), 3);
+        assert_eq!(List::keep_focus(height, padding, 5, 3, max, tall), 0);
+        // One tall row under the cursor pushes the view further down
+        let one = |i| if i == 12 { 10 } else { 1 };
+        assert_eq!(List::keep_focus(height, padding, 10, 0, max, one), 0);
+        assert_eq!(List::keep_focus(height, padding, 20, 0, max, one), 5);
+    }
+
+    fn visible(statuses: &[TaskStatus]) -> Visibility {
+        Visibility {
+            statuses: statuses.iter().copied().collect(),
+            hide_templates: false,
+        }
+    }
+
+    #[test]
+    fn hidden_statuses() {
+        let mut root = Task::new("Root".to_string());
+        root.complete();
+        let mut parent = Task::new("Parent".to_string());
+        parent.sub_of = Some(root.uuid);
+        parent.complete();
+        let mut child = Task::new("Child".to_string());
+        child.sub_of = Some(parent.uuid);
+        let mut done = Task::new("Done".to_string());
+        done.sub_of = Some(parent.uuid);
+        done.complete();
+        let tasks: HashMap<Uuid, Task> = vec![root.clone(), parent.clone(), child.clone(), done.clone()]
+            .into_iter()
+            .map(|t| (t.uuid, t))
+            .collect();
+
+        let placed = place(&tasks, &visible(&[TaskStatus::Pending]));
+        assert_eq!(placed.len(), 3);
+        assert!(!placed.contains_key(&done.uuid));
+        assert_eq!(placed[&child.uuid].parent, Some(parent.uuid));
+
+        let rows = Tree::new(&tasks, Separation::None, Hierarchy::SubOf, None, &visible(&[TaskStatus::Pending])).rows(&HashSet::new());
+        assert_eq!(rows.len(), 3);
+        let RowEntry::Task(row) = &rows[1] else {
+            panic!("expected a task");
+        };
+        assert_eq!(row.task, parent.uuid);
+        let progress = row.progress.unwrap();
+        assert_eq!((progress.done, progress.total), (1, 2));
+    }
+
+    #[test]
+    fn progress_ignores_blockers() {
+        let mut goal = Task::new("Goal".to_string());
+        let mut step = Task::new("Step".to_string());
+        step.sub_of = Some(goal.uuid);
+        step.complete();
+        let blocker = Task::new("Blocker".to_string());
+        goal.depends = vec![blocker.uuid];
+        let tasks: HashMap<Uuid, Task> = vec![goal.clone(), step, blocker.clone()]
+            .into_iter()
+            .map(|t| (t.uuid, t))
+            .collect();
+
+        let visibility = visible(&[TaskStatus::Pending, TaskStatus::Completed]);
+        for hierarchy in [Hierarchy::SubOf, Hierarchy::Depends] {
+            let rows = Tree::new(&tasks, Separation::None, hierarchy, None, &visibility).rows(&HashSet::new());
+            for row in &rows {
+                let RowEntry::Task(row) = row else {
+                    continue;
+                };
+                let progress = row.progress.map(|p| (p.done, p.total));
+                if row.task == goal.uuid {
+                    assert_eq!(progress, Some((1, 1)));
+                } else {
+                    assert_eq!(progress, None);
+                }
+            }
+        }
+    }
+
+    /// Trees of up to five subtasks each, with every third task done
+    fn synthetic(count: usize) -> HashMap<Uuid, Task> {
+        let mut tasks: Vec<Task> = Vec::with_capacity(count);
+        for i in 0..count {
+            let mut task = Task::new(format!("Task {}", i));
+            task.urgency = (i % 13) as f32;
+            if i % 100 != 0 {
+                task.sub_of = Some(tasks[i / 5].uuid);
+            }
+            if i % 3 == 0 {
+                task.complete();
+            }
+            tasks.push(task);
+        }
+        tasks.into_iter().map(|t| (t.uuid, t)).collect()
+    }
+
+    /// `cargo test --release benchmark_rows -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn benchmark_rows() {
+        use crossterm::event::{KeyEvent, KeyModifiers};
+
+        use crate::config::Config;
+
+        let tasks = synthetic(20_000);
+        let config = Config {
+            statuses: vec![TaskStatus::Pending, TaskStatus::Completed],
+            ..Config::default()
+        };
+        let start = std::time::Instant::now();
+        let mut list = List::new(&tasks, SharedConfig::new(config), SharedUdas::default(), None, SharedTracked::default());
+        let build = start.elapsed();
+        eprintln!("building the tree: {:?}", build);
+
+        let nodes = list.tree.nodes.as_ptr();
+        let key = |code| Event::Key(KeyEvent::new(code, KeyModifiers::NONE));
+        let start = std::time::Instant::now();
+        let len = list.rows.len();
+        for _ in 0..50 {
+            let after = list.cursor + 1;
+            list.cursor = after + list.rows[after..].iter().position(|r| r.fold_state() == FoldState::Open).unwrap();
+            list.event(key(KeyCode::Enter), &tasks);
+            assert!(list.rows.len() < len);
+            list.event(key(KeyCode::Enter), &tasks);
+            assert_eq!(list.rows.len(), len);
+        }
+        let fold = start.elapsed() / 100;
+        eprintln!("flattening, per fold: {:?}", fold);
+
+        // Folding only flattens the tree again, it's never rebuilt or sorted
+        assert_eq!(list.tree.nodes.as_ptr(), nodes);
+        assert!(fold < build, "folding took {:?}, building {:?}", fold, build);
+    }
+
+    /// `cargo test --release benchmark_draw -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn benchmark_draw() {
+        use ratatui::{backend::TestBackend, Terminal};
+
+        use crate::config::Config;
+
+        let tasks = synthetic(100_000);
+        let config = Config {
+            statuses: vec![TaskStatus::Pending, TaskStatus::Completed],
+            ..Config::default()
+        };
+        let mut list = List::new(&tasks, SharedConfig::new(config), SharedUdas::default(), None, SharedTracked::default());
+        let theme = SharedTheme::default();
+        let mut terminal = Terminal::new(TestBackend::new(120, 40)).unwrap();
+        let len = list.rows.len();
+        assert!(len > 100_000);
+
+        let mut times = vec![];
+        for cursor in [0, len / 2, len - 1] {
+            list.cursor = cursor;
+            list.focus = cursor.saturating_sub(20);
+            let start = std::time::Instant::now();
+            for _ in 0..100 {
+                terminal.draw(|f| list.draw(theme.clone(), f, f.size(), &tasks).unwrap()).unwrap();
+            }
+            let time = start.elapsed() / 100;
+            eprintln!("drawing at row {}: {:?}", cursor, time);
+            times.push(time);
+        }
+        // Only the rows on screen are drawn, so how far down they are doesn't matter
+        let fastest = *times.iter().min().unwrap();
+        let slowest = *times.iter().max().unwrap();
+        assert!(slowest < fastest * 3, "draws took {:?}", times);
+    }
+
+    #[test]
+    fn click_to_fold() {
+        use crossterm::event::KeyModifiers;
+        use ratatui::{backend::TestBackend, Terminal};
+
+        let parent = Task::new("Write the release notes for the next version".to_string());
+        let mut child = Task::new("Changelog".to_string());
+        child.sub_of = Some(parent.uuid);
+        let tasks: HashMap<Uuid, Task> = vec![parent.clone(), child.clone()]
+            .into_iter()
+            .map(|t| (t.uuid, t))
+            .collect();
+        let mut list = List::new(&tasks, SharedConfig::default(), SharedUdas::default(), None, SharedTracked::default());
+        let theme = SharedTheme::default();
+        let mut terminal = Terminal::new(TestBackend::new(30, 10)).unwrap();
+        let mut draw = |list: &mut List| {
+            terminal.draw(|f| list.draw(theme.clone(), f, f.size(), &tasks).unwrap()).unwrap();
+            terminal.backend().buffer().clone()
+        };
+        let click = |column, row| Event::Mouse(MouseEvent {
+            kind: MouseEventKind::Down(MouseButton::Left),
+            column,
+            row,
+            modifiers: KeyModifiers::NONE,
+        });
+
+        let buffer = draw(&mut list);
+        assert_eq!(list.rows.len(), 3);
+        // Rows are measured for scrolling the same as they're drawn
+        assert!(SharedTheme::ptr_eq(&list.theme, &theme));
+        for row in &list.layout {
+            assert_eq!(list.rows[row.index].height(&list, &tasks, list.description_width), row.area.height as usize);
+        }
+        // The description wraps, so the glyph is on a later line
+        let (x, y) = list.layout[1].fold.expect("parent has a fold glyph");
+        assert!(y > list.layout[1].area.y);
+        let glyph = theme.glyphs().fold_open.trim();
+        assert_eq!(buffer.get(x, y).symbol(), glyph);
+
+        list.event(click(0, list.layout[2].area.y), &tasks);
+        assert_eq!(list.cursor, 2);
+        assert_eq!(list.rows.len(), 3);
+        list.event(click(x, y), &tasks);
+        assert_eq!(list.cursor, 1);
+        assert_eq!(list.rows.len(), 2);
+    }
+
+    #[test]
+    fn taskrc_colors_whole_row() {
+        use ratatui::{backend::TestBackend, style::Color, Terminal};
+
+        use crate::{config::Config, taskrc::TaskRc};
+
+        let mut review = Task::new("Review".to_string());
+        review.tags = vec!["work".to_string()];
+        review.udas.insert("size".to_string(), serde_json::Value::String("L".to_string()));
+        let plain = Task::new("Plain".to_string());
+        let tasks: HashMap<Uuid, Task> = vec![review.clone(), plain.clone()]
+            .into_iter()
+            .map(|t| (t.uuid, t))
+            .collect();
+        let config = Config {
+            columns: vec!["state".to_string(), "description".to_string(), "size".to_string()],
+            ..Config::default()
+        };
+        let colors = SharedColors::new(TaskRc::parse("color.tag.work=magenta on blue\n").colors());
+        let mut list = List::new(&tasks, SharedConfig::new(config), SharedUdas::default(), Some(colors), SharedTracked::default());
+        let theme = SharedTheme::default();
+        let mut terminal = Terminal::new(TestBackend::new(40, 10)).unwrap();
+        terminal.draw(|f| list.draw(theme.clone(), f, f.size(), &tasks).unwrap()).unwrap();
+        let buffer = terminal.backend().buffer().clone();
+
+        let row = |uuid: Uuid| list.layout.iter()
+            .find(|r| matches!(&list.rows[r.index], RowEntry::Task(t) if t.task == uuid))
+            .expect("task is shown")
+            .area;
+        let area = row(review.uuid);
+        let state = buffer.get(area.x + 2, area.y);
+        assert_eq!((state.fg, state.bg), (Color::Magenta, Color::Blue));
+        let size = (area.x..area.right()).rev()
+            .map(|x| buffer.get(x, area.y))
+            .find(|c| c.symbol() == "L")
+            .expect("size is shown");
+        assert_eq!((size.fg, size.bg), (Color::Magenta, Color::Blue));
+        let area = row(plain.uuid);
+        assert_ne!(buffer.get(area.right() - 3, area.y).bg, Color::Blue);
+    }
+
+    #[test]
+    fn scroll_empty_list() {
+        use crossterm::event::KeyModifiers;
+        use ratatui::{backend::TestBackend, Terminal};
+
+        let mut done = Task::new("Done".to_string());
+        done.complete();
+        let tasks: HashMap<Uuid, Task> = HashMap::from([(done.uuid, done)]);
+        // Completed tasks are hidden, so there's nothing to show
+        let mut list = List::new(&tasks, SharedConfig::default(), SharedUdas::default(), None, SharedTracked::default());
+        let mut terminal = Terminal::new(TestBackend::new(30, 10)).unwrap();
+        terminal.draw(|f| list.draw(SharedTheme::default(), f, f.size(), &tasks).unwrap()).unwrap();
+        assert!(list.rows.is_empty());
+
+        for kind in [MouseEventKind::ScrollDown, MouseEventKind::ScrollUp] {
+            list.event(Event::Mouse(MouseEvent { kind, column: 5, row: 5, modifiers: KeyModifiers::NONE }), &tasks);
+            assert_eq!(list.cursor, 0);
+        }
+    }
+
+    #[test]
+    fn recurring_under_template() {
+        let mut template = Task::new("Water plants".to_string());
+        template.status = TaskStatus::Recurring;
+        template.recur = Some("weekly".to_string());
+        let mut instance = Task::new("Water plants".to_string());
+        instance.parent = Some(template.uuid);
+        let tasks: HashMap<Uuid, Task> = vec![template.clone(), instance.clone()]
+            .into_iter()
+            .map(|t| (t.uuid, t))
+            .collect();
+
+        let mut visibility = visible(&[TaskStatus::Pending]);
+        let placed = place(&tasks, &visibility);
+        assert_eq!(placed[&instance.uuid].parent, Some(template.uuid));
+        visibility.hide_templates = true;
+        let placed = place(&tasks, &visibility);
+        assert!(!placed.contains_key(&template.uuid));
+        assert_eq!(placed[&instance.uuid].parent, None);
+    }
+
+    #[test]
+    fn recurring_template_under_parent() {
+        let project = Task::new("Garden".to_string());
+        let mut template = Task::new("Water plants".to_string());
+        template.status = TaskStatus::Recurring;
+        template.recur = Some("weekly".to_string());
+        template.sub_of = Some(project.uuid);
+        let mut instance = Task::new("Water plants".to_string());
+        instance.parent = Some(template.uuid);
+        instance.sub_of = template.sub_of;
+        let tasks: HashMap<Uuid, Task> = vec![project.clone(), template.clone(), instance.clone()]
+            .into_iter()
+            .map(|t| (t.uuid, t))
+            .collect();
+
+        let placed = place(&tasks, &visible(&[TaskStatus::Pending]));
+        assert_eq!(placed[&template.uuid].parent, Some(project.uuid));
+        assert_eq!(placed[&instance.uuid].parent, Some(template.uuid));
+
+        let mut visibility = visible(&[TaskStatus::Pending]);
+        visibility.hide_templates = true;
+        let placed = place(&tasks, &visibility);
+        assert_eq!(placed[&instance.uuid].parent, Some(project.uuid));
+    }
+
+    #[test]
+    fn agenda_sections() {
+        let today = NaiveDate::from_ymd_opt(2024, 3, 10).unwrap();
+        // Noon local time, stored in UTC like Taskwarrior does
+        let at = |day: u32| {
+            let local = today.with_day(day).unwrap().and_hms_opt(12, 0, 0).unwrap();
+            Some(Local.from_local_datetime(&local).unwrap().naive_utc())
+        };
+        let mut task = Task::new("Standup".to_string());
+        assert_eq!(agenda_section(&task, today, 5), None);
+
+        task.scheduled = at(11);
+        assert_eq!(agenda_section(&task, today, 5), Some(3));
+        task.due = at(9);
+        assert_eq!(agenda_section(&task, today, 5), Some(1));
+        task.due = at(10);
+        assert_eq!(agenda_section(&task, today, 5), Some(2));
+        task.due = at(30);
+        assert_eq!(agenda_section(&task, today, 5), None);
+
+        task.start = at(10);
+        assert_eq!(agenda_section(&task, today, 5), Some(0));
+        task.complete();
+        assert_eq!(agenda_section(&task, today, 5), None);
     }
 
 }
@@ -1,2 +1,29 @@
+use std::collections::HashMap;
+
+use color_eyre::Result;
+use ratatui::{layout::Rect, Frame};
+use uuid::Uuid;
+
+use crate::{action::Action, data::Task, event::Event, ui::style::SharedTheme};
+
 pub mod list;
+pub mod kanban;
+pub mod calendar;
+
+/// A view of the tasks. Every tab shares the same tasks and theme, which the app owns.
+pub trait Tab: std::fmt::Debug {
+
+    fn title(&self) -> String;
+
+    fn draw(&mut self, theme: SharedTheme, frame: &mut Frame, area: Rect, tasks: &HashMap<Uuid, Task>) -> Result<()>;
+
+    /// Events while this tab is open. Anything that changes tasks has to go through actions.
+    fn event(&mut self, event: Event, tasks: &HashMap<Uuid, Task>) -> Vec<Action>;
+
+    /// Tasks were changed or reloaded
+    fn refresh(&mut self, tasks: &HashMap<Uuid, Task>);
+
+    /// Called on every tick, for anything that changes over time
+    fn tick(&mut self) {}
 
+}
@@ -0,0 +1,581 @@
+use std::{cmp::Ordering, collections::HashMap, fmt, path::{Path, PathBuf}, process::Command, rc::Rc};
+
+use chrono::{Duration, NaiveDateTime};
+use ratatui::style::{Color, Modifier, Style};
+use color_eyre::{eyre::eyre, Result};
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::data::{self, Task, TaskStatus};
+
+pub type SharedUdas = Rc<UdaSchema>;
+pub type SharedColors = Rc<TaskColors>;
+
+const DATE_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+/// Taskwarrior's default `rule.precedence.color`, most important first
+const COLOR_PRECEDENCE: &str = "deleted,completed,active,keyword.,tag.,project.,overdue,scheduled,due.today,due,blocked,blocking,recurring,tagged,uda.";
+
+/// Settings from Taskwarrior's configuration.
+///
+/// This is a flat `key=value` map, the same format `task _show` prints.
+#[derive(Debug, Clone, Default)]
+pub struct TaskRc {
+    settings: HashMap<String, String>,
+}
+
+impl TaskRc {
+
+    /// Loads the configuration through `task _show`, falling back to reading the taskrc file
+    /// directly if Taskwarrior can't be run.
+    pub fn load() -> Result<TaskRc> {
+        if let Ok(output) = Command::new("task").arg("_show").output() {
+            if output.status.success() {
+                return Ok(TaskRc::parse(&String::from_utf8_lossy(&output.stdout)));
+            }
+        }
+        TaskRc::from_file(TaskRc::default_path().ok_or_else(|| eyre!("could not find taskrc"))?)
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<TaskRc> {
+        let contents = std::fs::read_to_string(path)?;
+        Ok(TaskRc::parse(&contents))
+    }
+
+    /// `$TASKRC`, or `~/.taskrc`
+    fn default_path() -> Option<PathBuf> {
+        if let Ok(path) = std::env::var("TASKRC") {
+            return Some(path.into());
+        }
+        std::env::var("HOME").ok().map(|h| PathBuf::from(h).join(".taskrc"))
+    }
+
+    pub fn parse(contents: &str) -> TaskRc {
+        let settings = contents.lines()
+            .map(|l| l.trim())
+            // Includes would need the whole file tree, `task _show` already resolves them
+            .filter(|l| !l.is_empty() && !l.starts_with('#') && !l.starts_with("include "))
+            .filter_map(|l| l.split_once('='))
+            .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+            .collect();
+        TaskRc { settings }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.settings.get(key).map(|s| s.as_str())
+    }
+
+    pub fn udas(&self) -> UdaSchema {
+        let mut udas: HashMap<String, UdaDefinition> = HashMap::new();
+        for (key, value) in &self.settings {
+            let Some(rest) = key.strip_prefix("uda.") else {
+                continue;
+            };
+            let Some((name, attribute)) = rest.rsplit_once('.') else {
+                continue;
+            };
+            let uda = udas.entry(name.to_string()).or_insert_with(|| UdaDefinition::new(name.to_string()));
+            match attribute {
+                "type" => uda.kind = UdaType::from_name(value),
+                "label" => uda.label = value.clone(),
+                "values" => uda.values = value.split(',').map(|v| v.trim().to_string()).collect(),
+                _ => {}
+            }
+        }
+        UdaSchema { udas }
+    }
+
+    /// `color.*` rules, in the order of `rule.precedence.color`. Rules with colors that can't be
+    /// read are left out, and there are none when `color` is off.
+    pub fn colors(&self) -> TaskColors {
+        let due_days = self.get("due").and_then(|d| d.parse().ok()).unwrap_or(7);
+        if self.get("color").is_some_and(|c| ["off", "no", "n", "false", "0"].contains(&c.trim().to_lowercase().as_str())) {
+            return TaskColors { rules: vec![], due_days };
+        }
+        let precedence = self.get("rule.precedence.color").unwrap_or(COLOR_PRECEDENCE);
+        let mut rules = vec![];
+        for category in precedence.split(',').map(|c| c.trim()) {
+            let mut matching: Vec<(&str, ColorRule, Style)> = self.settings.iter()
+                .filter_map(|(key, value)| {
+                    let name = key.strip_prefix("color.")?;
+                    // Families like `tag.` are every setting under them
+                    let in_category = if category.ends_with('.') { name.starts_with(category) } else { name == category };
+                    if !in_category {
+                        return None;
+                    }
+                    Some((name, ColorRule::from_name(name)?, parse_color(value)?))
+                })
+                .collect();
+            // Settings aren't ordered, so families are sorted by name to always blend the same
+            matching.sort_by_key(|(name, _, _)| *name);
+            rules.extend(matching.into_iter().map(|(_, rule, style)| (rule, style)));
+        }
+        TaskColors { rules, due_days }
+    }
+
+}
+
+/// What a `color.*` setting applies to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ColorRule {
+    Deleted,
+    Completed,
+    Active,
+    /// Description contains it
+    Keyword(String),
+    /// `none` is for tasks without tags
+    Tag(String),
+    /// Includes subprojects, `none` is for tasks without one
+    Project(String),
+    Overdue,
+    Scheduled,
+    DueToday,
+    /// Due within `due` days
+    Due,
+    Blocked,
+    Blocking,
+    Recurring,
+    Tagged,
+    /// Has the UDA, or has it set to the value
+    Uda(String, Option<String>),
+}
+
+impl ColorRule {
+
+    fn from_name(name: &str) -> Option<ColorRule> {
+        let rule = match name {
+            "deleted" => ColorRule::Deleted,
+            "completed" => ColorRule::Completed,
+            "active" => ColorRule::Active,
+            "overdue" => ColorRule::Overdue,
+            "scheduled" => ColorRule::Scheduled,
+            "due.today" => ColorRule::DueToday,
+            "due" => ColorRule::Due,
+            "blocked" => ColorRule::Blocked,
+            "blocking" => ColorRule::Blocking,
+            "recurring" => ColorRule::Recurring,
+            "tagged" => ColorRule::Tagged,
+            _ => {
+                let (family, rest) = name.split_once('.')?;
+                match family {
+                    "keyword" => ColorRule::Keyword(rest.to_string()),
+                    "tag" => ColorRule::Tag(rest.to_string()),
+                    "project" => ColorRule::Project(rest.to_string()),
+                    "uda" => match rest.split_once('.') {
+                        Some((uda, value)) => ColorRule::Uda(uda.to_string(), Some(value.to_string())),
+                        None => ColorRule::Uda(rest.to_string(), None),
+                    },
+                    _ => return None,
+                }
+            },
+        };
+        Some(rule)
+    }
+
+}
+
+/// Colors rows the way Taskwarrior colors reports
+#[derive(Debug, Clone, Default)]
+pub struct TaskColors {
+    /// Most important first
+    rules: Vec<(ColorRule, Style)>,
+    due_days: i64,
+}
+
+impl TaskColors {
+
+    /// Every rule that applies, blended so more important rules win. `None` if none apply.
+    pub fn style(&self, task: &Task, now: NaiveDateTime, blocked: bool, blocking: bool) -> Option<Style> {
+        let matching = |rule: &ColorRule| {
+            let unresolved = !task.is_resolved();
+            let due = task.days_until_due(now);
+            match rule {
+                ColorRule::Deleted => task.status == TaskStatus::Deleted,
+                ColorRule::Completed => task.status == TaskStatus::Completed,
+                ColorRule::Active => task.start.is_some(),
+                ColorRule::Keyword(word) => task.description.contains(word.as_str()),
+                ColorRule::Tag(tag) if tag == "none" => task.tags.is_empty(),
+                ColorRule::Tag(tag) => task.tags.contains(tag),
+                ColorRule::Project(project) if project == "none" => task.project.is_none(),
+                ColorRule::Project(project) => task.project.as_ref().is_some_and(|p| {
+                    p == project || p.strip_prefix(project.as_str()).is_some_and(|rest| rest.starts_with('.'))
+                }),
+                ColorRule::Overdue => unresolved && due.is_some_and(|d| d < 0),
+                ColorRule::Scheduled => task.scheduled.is_some(),
+                ColorRule::DueToday => unresolved && due == Some(0),
+                ColorRule::Due => unresolved && due.is_some_and(|d| d <= self.due_days),
+                ColorRule::Blocked => blocked,
+                ColorRule::Blocking => blocking,
+                ColorRule::Recurring => task.recur.is_some(),
+                ColorRule::Tagged => !task.tags.is_empty(),
+                ColorRule::Uda(name, value) => match (task.udas.get(name), value) {
+                    (None, _) => false,
+                    (Some(_), None) => true,
+                    (Some(set), Some(value)) => set.as_str() == Some(value.as_str()),
+                },
+            }
+        };
+        self.rules.iter()
+            .rev()
+            .filter(|(rule, _)| matching(rule))
+            .map(|(_, style)| *style)
+            .reduce(|blended, style| blended.patch(style))
+    }
+
+}
+
+/// Reads a Taskwarrior color like `bold red on bright white`, `color202`, `rgb025` or `gray8`
+pub fn parse_color(spec: &str) -> Option<Style> {
+    let mut style = Style::default();
+    let (fg, bg) = match spec.split_once(" on ") {
+        Some((fg, bg)) => (fg, Some(bg)),
+        None => match spec.trim().strip_prefix("on ") {
+            Some(bg) => ("", Some(bg)),
+            None => (spec, None),
+        },
+    };
+    let mut bright = false;
+    for word in fg.split_whitespace() {
+        match word {
+            "bold" => style = style.add_modifier(Modifier::BOLD),
+            "underline" => style = style.add_modifier(Modifier::UNDERLINED),
+            "inverse" => style = style.add_modifier(Modifier::REVERSED),
+            "bright" => bright = true,
+            color => style = style.fg(parse_color_name(color, bright)?),
+        }
+    }
+    if let Some(bg) = bg {
+        let mut words: Vec<&str> = bg.split_whitespace().collect();
+        let bright = words.first() == Some(&"bright");
+        if bright {
+            words.remove(0);
+        }
+        let [color] = words.as_slice() else {
+            return None;
+        };
+        style = style.bg(parse_color_name(color, bright)?);
+    }
+    Some(style)
+}
+
+fn parse_color_name(name: &str, bright: bool) -> Option<Color> {
+    let color = match (name, bright) {
+        ("black", false) => Color::Black,
+        ("black", true) => Color::DarkGray,
+        ("red", false) => Color::Red,
+        ("red", true) => Color::LightRed,
+        ("green", false) => Color::Green,
+        ("green", true) => Color::LightGreen,
+        ("yellow", false) => Color::Yellow,
+        ("yellow", true) => Color::LightYellow,
+        ("blue", false) => Color::Blue,
+        ("blue", true) => Color::LightBlue,
+        ("magenta", false) => Color::Magenta,
+        ("magenta", true) => Color::LightMagenta,
+        ("cyan", false) => Color::Cyan,
+        ("cyan", true) => Color::LightCyan,
+        ("white", false) => Color::Gray,
+        ("white", true) => Color::White,
+        _ => {
+            if let Some(n) = name.strip_prefix("color") {
+                Color::Indexed(n.parse().ok()?)
+            } else if let Some(n) = name.strip_prefix("gray").or_else(|| name.strip_prefix("grey")) {
+                let n: u8 = n.parse().ok()?;
+                Color::Indexed(232 + n.min(23))
+            } else if let Some(rgb) = name.strip_prefix("rgb") {
+                // A 6x6x6 cube after the 16 basic colors
+                let digits: Vec<u8> = rgb.chars().map(|c| c.to_digit(6).map(|d| d as u8)).collect::<Option<_>>()?;
+                let [r, g, b] = digits.as_slice() else {
+                    return None;
+                };
+                Color::Indexed(16 + 36 * r + 6 * g + b)
+            } else {
+                return None;
+            }
+        },
+    };
+    Some(color)
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum UdaType {
+    String,
+    Numeric,
+    Date,
+    Duration,
+    Uuid,
+}
+
+impl UdaType {
+
+    fn from_name(name: &str) -> UdaType {
+        match name {
+            "numeric" => UdaType::Numeric,
+            "date" => UdaType::Date,
+            "duration" => UdaType::Duration,
+            "uuid" => UdaType::Uuid,
+            _ => UdaType::String,
+        }
+    }
+
+}
+
+#[derive(Debug, Clone)]
+pub struct UdaDefinition {
+    pub name: String,
+    pub label: String,
+    pub kind: UdaType,
+    /// Allowed values, in the order Taskwarrior sorts them
+    pub values: Vec<String>,
+}
+
+impl UdaDefinition {
+
+    pub fn new(name: String) -> UdaDefinition {
+        UdaDefinition {
+            label: name.clone(),
+            name,
+            kind: UdaType::String,
+            values: vec![],
+        }
+    }
+
+    pub fn parse(&self, value: &Value) -> Option<UdaValue> {
+        match self.kind {
+            UdaType::String => match value {
+                Value::String(s) => Some(UdaValue::String(s.clone())),
+                Value::Null => None,
+                v => Some(UdaValue::String(v.to_string())),
+            },
+            UdaType::Numeric => match value {
+                Value::Number(n) => n.as_f64().map(UdaValue::Numeric),
+                Value::String(s) => s.parse().ok().map(UdaValue::Numeric),
+                _ => None,
+            },
+            UdaType::Date => value.as_str()
+                .and_then(|s| NaiveDateTime::parse_from_str(s, DATE_FORMAT).ok())
+                .map(UdaValue::Date),
+            UdaType::Duration => match value {
+                Value::Number(n) => n.as_i64().map(|s| UdaValue::Duration(Duration::seconds(s))),
+                Value::String(s) => parse_duration(s).map(UdaValue::Duration),
+                _ => None,
+            },
+            UdaType::Uuid => value.as_str()
+                .and_then(|s| Uuid::parse_str(s).ok())
+                .map(UdaValue::Uuid),
+        }
+    }
+
+    /// Compares two values the way Taskwarrior would sort this UDA.
+    ///
+    /// Missing values always go last.
+    pub fn compare(&self, a: Option<&UdaValue>, b: Option<&UdaValue>) -> Ordering {
+        match (a, b) {
+            (None, None) => Ordering::Equal,
+            (None, Some(_)) => Ordering::Greater,
+            (Some(_), None) => Ordering::Less,
+            (Some(UdaValue::String(a)), Some(UdaValue::String(b))) if !self.values.is_empty() => {
+                let position = |v: &String| self.values.iter().position(|p| p == v).unwrap_or(self.values.len());
+                position(a).cmp(&position(b))
+            },
+            (Some(a), Some(b)) => a.partial_cmp(b).unwrap_or(Ordering::Equal),
+        }
+    }
+
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct UdaSchema {
+    udas: HashMap<String, UdaDefinition>,
+}
+
+impl UdaSchema {
+
+    pub fn get(&self, name: &str) -> Option<&UdaDefinition> {
+        self.udas.get(name)
+    }
+
+}
+
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub enum UdaValue {
+    String(String),
+    Numeric(f64),
+    Date(NaiveDateTime),
+    Duration(Duration),
+    Uuid(Uuid),
+}
+
+impl fmt::Display for UdaValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UdaValue::String(s) => write!(f, "{}", s),
+            UdaValue::Numeric(n) => write!(f, "{}", n),
+            UdaValue::Date(d) => write!(f, "{}", data::to_local(d).format("%Y-%m-%d")),
+            UdaValue::Duration(d) => write!(f, "{}", format_duration(d)),
+            UdaValue::Uuid(u) => write!(f, "{}", &u.to_string()[..8]),
+        }
+    }
+}
+
+/// Parses an ISO 8601 duration (`P1DT2H`), or a plain number of seconds which older
+/// versions of Taskwarrior export.
+pub fn parse_duration(s: &str) -> Option<Duration> {
+    if let Ok(seconds) = s.parse::<i64>() {
+        return Some(Duration::seconds(seconds));
+    }
+    let rest = s.strip_prefix('P')?;
+    let mut total = Duration::zero();
+    let mut number = String::new();
+    let mut time = false;
+    for c in rest.chars() {
+        match c {
+            'T' => time = true,
+            '0'..='9' | '.' => number.push(c),
+            unit => {
+                let n: f64 = number.parse().ok()?;
+                number.clear();
+                let seconds = match (unit, time) {
+                    ('Y', false) => n * 365.0 * 86400.0,
+                    ('M', false) => n * 30.0 * 86400.0,
+                    ('W', false) => n * 7.0 * 86400.0,
+                    ('D', false) => n * 86400.0,
+                    ('H', true) => n * 3600.0,
+                    ('M', true) => n * 60.0,
+                    ('S', true) => n,
+                    _ => return None,
+                };
+                total += Duration::seconds(seconds as i64);
+            }
+        }
+    }
+    if !number.is_empty() {
+        return None;
+    }
+    Some(total)
+}
+
+pub fn format_duration(d: &Duration) -> String {
+    let days = d.num_days();
+    let hours = d.num_hours() % 24;
+    let minutes = d.num_minutes() % 60;
+    if days > 0 {
+        format!("{}d {}h", days, hours)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else {
+        format!("{}m", minutes)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uda_schema() {
+        let rc = TaskRc::parse("
+            # comment
+            uda.estimate.type=duration
+            uda.estimate.label=Est
+            uda.size.type=string
+            uda.size.values=L,M,S
+            uda.sub_of.type=string
+            color.due=red
+        ");
+        let udas = rc.udas();
+        let estimate = udas.get("estimate").expect("uda went missing");
+        assert_eq!(estimate.kind, UdaType::Duration);
+        assert_eq!(estimate.label, "Est");
+        let size = udas.get("size").expect("uda went missing");
+        assert_eq!(size.values, vec!["L", "M", "S"]);
+        assert!(udas.get("sub_of").is_some());
+        assert!(udas.get("due").is_none());
+        assert_eq!(rc.get("color.due"), Some("red"));
+    }
+
+    #[test]
+    fn uda_compare() {
+        let mut size = UdaDefinition::new("size".to_string());
+        size.values = vec!["L".to_string(), "M".to_string(), "S".to_string()];
+        let l = size.parse(&Value::String("L".to_string()));
+        let s = size.parse(&Value::String("S".to_string()));
+        assert_eq!(size.compare(l.as_ref(), s.as_ref()), Ordering::Less);
+        assert_eq!(size.compare(None, s.as_ref()), Ordering::Greater);
+
+        let mut estimate = UdaDefinition::new("estimate".to_string());
+        estimate.kind = UdaType::Numeric;
+        let two = estimate.parse(&serde_json::json!(2));
+        let ten = estimate.parse(&serde_json::json!(10));
+        assert_eq!(estimate.compare(two.as_ref(), ten.as_ref()), Ordering::Less);
+    }
+
+    #[test]
+    fn local_dates() {
+        use chrono::{Local, NaiveDate, TimeZone};
+
+        // Just after midnight local time is still the day before in UTC east of it
+        let local = NaiveDate::from_ymd_opt(2024, 3, 10).unwrap().and_hms_opt(0, 30, 0).unwrap();
+        let utc = Local.from_local_datetime(&local).unwrap().naive_utc();
+        let mut review = UdaDefinition::new("review".to_string());
+        review.kind = UdaType::Date;
+        let value = review.parse(&Value::String(utc.format(DATE_FORMAT).to_string())).unwrap();
+        assert_eq!(value.to_string(), "2024-03-10");
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_duration("PT1H30M"), Some(Duration::minutes(90)));
+        assert_eq!(parse_duration("P1DT2H"), Some(Duration::hours(26)));
+        assert_eq!(parse_duration("P2W"), Some(Duration::days(14)));
+        assert_eq!(parse_duration("3600"), Some(Duration::hours(1)));
+        assert_eq!(parse_duration("1 hour"), None);
+    }
+
+    #[test]
+    fn colors() {
+        assert_eq!(parse_color("bold red on bright white"), Some(Style::default().fg(Color::Red).bg(Color::White).add_modifier(Modifier::BOLD)));
+        assert_eq!(parse_color("on color202"), Some(Style::default().bg(Color::Indexed(202))));
+        assert_eq!(parse_color("rgb025"), Some(Style::default().fg(Color::Indexed(16 + 2 * 6 + 5))));
+        assert_eq!(parse_color("gray8"), Some(Style::default().fg(Color::Indexed(240))));
+        assert_eq!(parse_color("bright black"), Some(Style::default().fg(Color::DarkGray)));
+        assert_eq!(parse_color(""), Some(Style::default()));
+        assert_eq!(parse_color("rgb9"), None);
+        assert_eq!(parse_color("purple"), None);
+
+        let rc = TaskRc::parse("
+            color.active=bold
+            color.tag.work=blue
+            color.project.home=green
+            color.due.today=on red
+            color.overdue=yellow
+            color.uda.size.L=magenta
+            color.recurring=purple
+        ");
+        let colors = rc.colors();
+        let now = NaiveDateTime::parse_from_str("20240310T120000Z", DATE_FORMAT).unwrap();
+
+        let mut task = Task::new("Water plants".to_string());
+        assert_eq!(colors.style(&task, now, false, false), None);
+        // Unknown colors are left out
+        task.recur = Some("weekly".to_string());
+        assert_eq!(colors.style(&task, now, false, false), None);
+
+        task.project = Some("home.garden".to_string());
+        assert_eq!(colors.style(&task, now, false, false), Some(Style::default().fg(Color::Green)));
+        task.due = Some(now);
+        assert_eq!(colors.style(&task, now, false, false), Some(Style::default().fg(Color::Green).bg(Color::Red)));
+        // Tags go before projects
+        task.tags = vec!["work".to_string()];
+        task.start = Some(now);
+        assert_eq!(colors.style(&task, now, false, false), Some(Style::default().fg(Color::Blue).bg(Color::Red).add_modifier(Modifier::BOLD)));
+
+        let mut sized = Task::new("Move".to_string());
+        sized.udas.insert("size".to_string(), Value::String("L".to_string()));
+        assert_eq!(colors.style(&sized, now, false, false), Some(Style::default().fg(Color::Magenta)));
+
+        let off = TaskRc::parse("color=off\ncolor.uda.size.L=magenta\n").colors();
+        assert_eq!(off.style(&sized, now, false, false), None);
+    }
+}
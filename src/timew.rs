@@ -0,0 +1,153 @@
+use std::{cell::RefCell, collections::{HashMap, HashSet}, process::Command, rc::Rc};
+
+use chrono::{Duration, NaiveDateTime};
+use color_eyre::{eyre::eyre, Result};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::{config::TimewarriorConfig, data::Task};
+
+/// A tracked interval from `timew export`. Intervals that are still running have no end.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Interval {
+    #[serde(with = "crate::data::date_parser")]
+    pub start: NaiveDateTime,
+    #[serde(with = "crate::data::optional_date_parser", default)]
+    pub end: Option<NaiveDateTime>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl Interval {
+
+    pub fn duration(&self, now: NaiveDateTime) -> Duration {
+        self.end.unwrap_or(now) - self.start
+    }
+
+}
+
+/// Reads the configured export file, or runs `timew export`
+pub fn load(config: &TimewarriorConfig) -> Result<Vec<Interval>> {
+    let contents = match &config.file {
+        Some(file) => std::fs::read_to_string(file)?,
+        None => {
+            let output = Command::new("timew").arg("export").output()?;
+            if !output.status.success() {
+                return Err(eyre!("`timew export` failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+            }
+            String::from_utf8_lossy(&output.stdout).to_string()
+        },
+    };
+    Ok(serde_json::from_str(&contents)?)
+}
+
+/// Reloaded by the app along with the tasks, and read by every list
+pub type SharedTracked = Rc<RefCell<Tracked>>;
+
+/// Time tracked per task, by itself and together with its subtasks
+#[derive(Debug, Clone, Default)]
+pub struct Tracked {
+    own: HashMap<Uuid, Duration>,
+    total: HashMap<Uuid, Duration>,
+}
+
+impl Tracked {
+
+    /// Intervals belong to a task when they're tagged with its uuid, or otherwise with its
+    /// description, which is what Timewarrior's Taskwarrior hook does. Descriptions that more
+    /// than one task has, like those of recurring instances, can't tell which one it was.
+    pub fn new(intervals: &[Interval], tasks: &HashMap<Uuid, Task>, now: NaiveDateTime) -> Tracked {
+        let mut by_description: HashMap<&str, Option<Uuid>> = HashMap::new();
+        for task in tasks.values() {
+            by_description.entry(task.description.as_str())
+                .and_modify(|uuid| *uuid = None)
+                .or_insert(Some(task.uuid));
+        }
+
+        let mut own: HashMap<Uuid, Duration> = HashMap::new();
+        for interval in intervals {
+            let task = interval.tags.iter()
+                .find_map(|tag| Uuid::parse_str(tag).ok().filter(|u| tasks.contains_key(u)))
+                .or_else(|| interval.tags.iter().find_map(|tag| by_description.get(tag.as_str()).copied().flatten()));
+            if let Some(uuid) = task {
+                *own.entry(uuid).or_insert_with(Duration::zero) += interval.duration(now);
+            }
+        }
+
+        let mut total: HashMap<Uuid, Duration> = HashMap::new();
+        for (uuid, duration) in &own {
+            let mut seen = HashSet::new();
+            let mut current = Some(*uuid);
+            while let Some(id) = current.filter(|id| seen.insert(*id)) {
+                *total.entry(id).or_insert_with(Duration::zero) += *duration;
+                current = tasks.get(&id).and_then(|t| t.sub_of);
+            }
+        }
+
+        Tracked { own, total }
+    }
+
+    pub fn own(&self, uuid: &Uuid) -> Option<Duration> {
+        self.own.get(uuid).copied()
+    }
+
+    /// Including every subtask
+    pub fn total(&self, uuid: &Uuid) -> Option<Duration> {
+        self.total.get(uuid).copied()
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tracked_per_task() {
+        let parent = Task::new("Release".to_string());
+        let mut child = Task::new("Write changelog".to_string());
+        child.sub_of = Some(parent.uuid);
+        let other = Task::new("Unrelated".to_string());
+        let tasks: HashMap<Uuid, Task> = vec![parent.clone(), child.clone(), other.clone()]
+            .into_iter()
+            .map(|t| (t.uuid, t))
+            .collect();
+
+        let json = format!(r#"[
+            {{"id":3,"start":"20240101T100000Z","end":"20240101T110000Z","tags":["Write changelog","docs"]}},
+            {{"id":2,"start":"20240101T120000Z","end":"20240101T123000Z","tags":["{}"]}},
+            {{"id":1,"start":"20240101T130000Z","tags":["Release"]}}
+        ]"#, child.uuid);
+        let intervals: Vec<Interval> = serde_json::from_str(&json).unwrap();
+        let now = NaiveDateTime::parse_from_str("20240101T131500Z", "%Y%m%dT%H%M%SZ").unwrap();
+        let tracked = Tracked::new(&intervals, &tasks, now);
+
+        assert_eq!(tracked.own(&child.uuid), Some(Duration::minutes(90)));
+        assert_eq!(tracked.total(&child.uuid), Some(Duration::minutes(90)));
+        assert_eq!(tracked.own(&parent.uuid), Some(Duration::minutes(15)));
+        assert_eq!(tracked.total(&parent.uuid), Some(Duration::minutes(105)));
+        assert_eq!(tracked.total(&other.uuid), None);
+    }
+
+    #[test]
+    fn ambiguous_descriptions() {
+        let first = Task::new("Water plants".to_string());
+        let second = Task::new("Water plants".to_string());
+        let tasks: HashMap<Uuid, Task> = vec![first.clone(), second.clone()]
+            .into_iter()
+            .map(|t| (t.uuid, t))
+            .collect();
+
+        let json = format!(r#"[
+            {{"id":2,"start":"20240101T100000Z","end":"20240101T110000Z","tags":["Water plants"]}},
+            {{"id":1,"start":"20240102T100000Z","end":"20240102T103000Z","tags":["Water plants","{}"]}}
+        ]"#, second.uuid);
+        let intervals: Vec<Interval> = serde_json::from_str(&json).unwrap();
+        let now = NaiveDateTime::parse_from_str("20240103T000000Z", "%Y%m%dT%H%M%SZ").unwrap();
+        let tracked = Tracked::new(&intervals, &tasks, now);
+
+        assert_eq!(tracked.own(&first.uuid), None);
+        assert_eq!(tracked.own(&second.uuid), Some(Duration::minutes(30)));
+    }
+}
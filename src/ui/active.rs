@@ -0,0 +1,41 @@
+use chrono::NaiveDateTime;
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    text::{Line, Span},
+};
+
+use crate::data::{self, Task};
+
+use super::style::SharedTheme;
+
+/// One line with every started task and how long it's been going.
+pub struct ActiveWidget<'a> {
+    tasks: Vec<&'a Task>,
+    now: NaiveDateTime,
+    theme: SharedTheme,
+}
+
+impl<'a> ActiveWidget<'a> {
+
+    pub fn new(mut tasks: Vec<&'a Task>, now: NaiveDateTime, theme: SharedTheme) -> ActiveWidget<'a> {
+        // Longest running first
+        tasks.sort_by_key(|t| t.start);
+        ActiveWidget { tasks, now, theme }
+    }
+
+    pub fn render(self, area: Rect, buf: &mut Buffer) {
+        let mut spans = vec![Span::styled(self.theme.glyphs().active, self.theme.marker())];
+        for (i, task) in self.tasks.iter().enumerate() {
+            if i > 0 {
+                spans.push(Span::styled(format!(" {} ", self.theme.glyphs().separator), self.theme.border()));
+            }
+            spans.push(Span::styled(task.description.clone(), self.theme.text()));
+            if let Some(elapsed) = task.elapsed(self.now) {
+                spans.push(Span::styled(format!(" {}", data::format_elapsed(elapsed)), self.theme.marker()));
+            }
+        }
+        buf.set_line(area.x, area.y, &Line::from(spans), area.width);
+    }
+
+}
@@ -0,0 +1,61 @@
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph, Widget},
+};
+
+use crate::backend::{self, Change};
+
+use super::style::SharedTheme;
+
+/// Staged changes, listed per task and then per field.
+pub struct ChangesWidget<'a> {
+    changes: &'a [Change],
+    theme: SharedTheme,
+}
+
+impl<'a> ChangesWidget<'a> {
+
+    pub fn new(changes: &'a [Change], theme: SharedTheme) -> ChangesWidget<'a> {
+        ChangesWidget { changes, theme }
+    }
+
+    fn lines(&self) -> Vec<Line<'a>> {
+        let mut lines = vec![];
+        for change in self.changes {
+            lines.push(Line::styled(change.after.description.clone(), self.theme.text()));
+            for field in backend::field_changes(change).unwrap_or_default() {
+                lines.push(Line::from(vec![
+                    Span::styled(format!("  {}: ", field.field), self.theme.fold()),
+                    Span::styled(field.before.unwrap_or_else(|| "-".to_string()), self.theme.text()),
+                    Span::styled(format!(" {} ", self.theme.glyphs().arrow), self.theme.marker()),
+                    Span::styled(field.after.unwrap_or_else(|| "-".to_string()), self.theme.text()),
+                ]));
+            }
+        }
+        lines
+    }
+
+    /// Lines needed to show everything, including the title
+    pub fn height(&self) -> u16 {
+        self.lines().len() as u16 + 1
+    }
+
+    pub fn render(self, area: Rect, buf: &mut Buffer) {
+        let title = if self.changes.is_empty() {
+            " Dry run, nothing staged ".to_string()
+        } else {
+            format!(" Pending changes ({}) [W]rite [X] discard ", self.changes.len())
+        };
+        let block = Block::default()
+            .borders(Borders::TOP)
+            .border_set(self.theme.glyphs().border)
+            .border_style(self.theme.border())
+            .title(Span::styled(title, self.theme.text()));
+        Paragraph::new(self.lines())
+            .block(block)
+            .render(area, buf);
+    }
+
+}
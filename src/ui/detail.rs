@@ -0,0 +1,84 @@
+use chrono::NaiveDateTime;
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::Style,
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph, Widget, Wrap},
+};
+
+use crate::{data::{self, Task}, timew::Tracked};
+
+use super::{row, style::SharedTheme};
+
+/// Fields of a single task, next to the list.
+pub struct DetailWidget<'a> {
+    task: &'a Task,
+    tracked: &'a Tracked,
+    now: NaiveDateTime,
+    theme: SharedTheme,
+}
+
+impl<'a> DetailWidget<'a> {
+
+    pub fn new(task: &'a Task, tracked: &'a Tracked, now: NaiveDateTime, theme: SharedTheme) -> DetailWidget<'a> {
+        DetailWidget { task, tracked, now, theme }
+    }
+
+    fn field(&self, name: &str, value: String) -> Line<'a> {
+        self.styled_field(name, value, self.theme.text())
+    }
+
+    fn styled_field(&self, name: &str, value: String, style: Style) -> Line<'a> {
+        Line::from(vec![
+            Span::styled(format!("{:<10}", name), self.theme.fold()),
+            Span::styled(value, style),
+        ])
+    }
+
+    fn lines(&self) -> Vec<Line<'a>> {
+        let task = self.task;
+        let date = |d: &NaiveDateTime| data::to_local(d).format("%Y-%m-%d %H:%M").to_string();
+        let mut lines = vec![
+            Line::styled(task.description.clone(), row::task::description_style(task, &self.theme, self.now)),
+            Line::default(),
+            self.styled_field("Status", task.status.to_string(), self.theme.status(&task.status)),
+        ];
+        if let Some(project) = &task.project {
+            lines.push(self.styled_field("Project", project.clone(), self.theme.project()));
+        }
+        if !task.tags.is_empty() {
+            lines.push(self.styled_field("Tags", task.tags.join(" "), self.theme.tag()));
+        }
+        if let Some(due) = &task.due {
+            lines.push(self.field("Due", date(due)));
+        }
+        if let Some(scheduled) = &task.scheduled {
+            lines.push(self.field("Scheduled", date(scheduled)));
+        }
+        if let Some(elapsed) = task.elapsed(self.now) {
+            lines.push(self.field("Started", data::format_elapsed(elapsed)));
+        }
+        lines.push(self.field("Urgency", format!("{:.2}", task.urgency)));
+        let tracked = self.tracked;
+        if let Some(own) = tracked.own(&task.uuid) {
+            lines.push(self.field("Tracked", data::format_elapsed(own)));
+        }
+        if let Some(total) = tracked.total(&task.uuid).filter(|t| Some(*t) != tracked.own(&task.uuid)) {
+            lines.push(self.field("Subtree", data::format_elapsed(total)));
+        }
+        lines
+    }
+
+    pub fn render(self, area: Rect, buf: &mut Buffer) {
+        let block = Block::default()
+            .borders(Borders::LEFT)
+            .border_set(self.theme.glyphs().border)
+            .border_style(self.theme.border());
+        Paragraph::new(self.lines())
+            .block(block)
+            .wrap(Wrap { trim: false })
+            .render(area, buf);
+    }
+
+}
@@ -0,0 +1,179 @@
+use ratatui::symbols::border;
+use serde::{Deserialize, Serialize};
+
+/// Overrides the glyph set from the config, for terminals that can't show it
+const ENV: &str = "TASKSMITH_GLYPHS";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum GlyphSet {
+    /// Needs a patched font from nerdfonts.com
+    #[default]
+    Nerd,
+    Unicode,
+    Ascii,
+}
+
+impl GlyphSet {
+
+    pub fn from_name(name: &str) -> Option<GlyphSet> {
+        match name.trim().to_lowercase().as_str() {
+            "nerd" => Some(GlyphSet::Nerd),
+            "unicode" => Some(GlyphSet::Unicode),
+            "ascii" => Some(GlyphSet::Ascii),
+            _ => None,
+        }
+    }
+
+    /// `TASKSMITH_GLYPHS` if it's set to a known set, otherwise the configured one
+    pub fn detect(configured: GlyphSet) -> GlyphSet {
+        std::env::var(ENV).ok()
+            .and_then(|name| GlyphSet::from_name(&name))
+            .unwrap_or(configured)
+    }
+
+    pub fn glyphs(&self) -> Glyphs {
+        match self {
+            GlyphSet::Nerd => NERD,
+            GlyphSet::Unicode => UNICODE,
+            GlyphSet::Ascii => ASCII,
+        }
+    }
+
+}
+
+/// Everything drawn that isn't text or a box
+#[derive(Debug, Clone, Copy)]
+pub struct Glyphs {
+    pub fold_open: &'static str,
+    pub fold_close: &'static str,
+
+    pub status_blocked: &'static str,
+    pub status_completed: &'static str,
+    pub status_waiting: &'static str,
+    pub status_deleted: &'static str,
+    pub status_recurring: &'static str,
+    /// Repeated once per urgency level of pending tasks
+    pub urgency: &'static str,
+
+    pub blocked: &'static str,
+    pub blocking: &'static str,
+    pub cycle: &'static str,
+    pub active: &'static str,
+    pub annotation: &'static str,
+    pub recur: &'static str,
+
+    pub progress_done: &'static str,
+    pub progress_left: &'static str,
+
+    /// Branch, last branch and the line down past a level
+    pub guides: [&'static str; 3],
+    pub separator: &'static str,
+    pub arrow: &'static str,
+    pub dot: &'static str,
+    pub caret: &'static str,
+    pub border: border::Set,
+}
+
+impl Default for Glyphs {
+    fn default() -> Self {
+        NERD
+    }
+}
+
+pub const UNICODE: Glyphs = Glyphs {
+    fold_open: " ▾",
+    fold_close: " ▸",
+    status_blocked: "⊘",
+    status_completed: "✔",
+    status_waiting: "◷",
+    status_deleted: "✗",
+    status_recurring: "⟳",
+    urgency: "◼",
+    blocked: "⊘ ",
+    blocking: "⚑ ",
+    cycle: "↻ ",
+    active: "▶ ",
+    annotation: "✎ ",
+    recur: " ⟳ ",
+    progress_done: "█",
+    progress_left: "░",
+    guides: ["├─", "└─", "│ "],
+    separator: "│",
+    arrow: "→",
+    dot: "·",
+    caret: "▏",
+    border: border::PLAIN,
+};
+
+/// Unicode, with icons from nerd fonts where there are better ones
+pub const NERD: Glyphs = Glyphs {
+    fold_open: " \u{eaf3}",
+    fold_close: " \u{eacc}",
+    status_blocked: "\u{f467}",
+    status_completed: "\u{eab2}",
+    status_waiting: "\u{f43a}",
+    status_deleted: "\u{ea81}",
+    status_recurring: "\u{f01e}",
+    ..UNICODE
+};
+
+pub const ASCII: Glyphs = Glyphs {
+    fold_open: " v",
+    fold_close: " >",
+    status_blocked: "B",
+    status_completed: "x",
+    status_waiting: "w",
+    status_deleted: "-",
+    status_recurring: "r",
+    urgency: "*",
+    blocked: "! ",
+    blocking: "^ ",
+    cycle: "@ ",
+    active: "> ",
+    annotation: "# ",
+    recur: " ~ ",
+    progress_done: "#",
+    progress_left: ".",
+    guides: ["|-", "`-", "| "],
+    separator: "|",
+    arrow: "->",
+    dot: "-",
+    caret: "_",
+    border: border::Set {
+        top_left: "+",
+        top_right: "+",
+        bottom_left: "+",
+        bottom_right: "+",
+        vertical_left: "|",
+        vertical_right: "|",
+        horizontal_top: "-",
+        horizontal_bottom: "-",
+    },
+};
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ascii_is_ascii() {
+        let glyphs = GlyphSet::Ascii.glyphs();
+        let border = glyphs.border;
+        let all = [
+            glyphs.fold_open, glyphs.fold_close, glyphs.status_blocked, glyphs.status_completed,
+            glyphs.status_waiting, glyphs.status_deleted, glyphs.status_recurring, glyphs.urgency,
+            glyphs.blocked, glyphs.blocking, glyphs.cycle, glyphs.active, glyphs.annotation,
+            glyphs.recur, glyphs.progress_done, glyphs.progress_left, glyphs.separator,
+            glyphs.arrow, glyphs.dot, glyphs.caret, border.top_left, border.top_right,
+            border.bottom_left, border.bottom_right, border.vertical_left, border.vertical_right,
+            border.horizontal_top, border.horizontal_bottom,
+        ];
+        for glyph in all.iter().chain(glyphs.guides.iter()) {
+            assert!(glyph.is_ascii(), "{:?} isn't ascii", glyph);
+        }
+        assert_eq!(GlyphSet::from_name(" ASCII\n"), Some(GlyphSet::Ascii));
+        assert_eq!(GlyphSet::from_name("emoji"), None);
+    }
+}
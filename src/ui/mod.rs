@@ -1,3 +1,9 @@
 pub mod tasklist;
 pub mod style;
 pub mod row;
+pub mod prompt;
+pub mod changes;
+pub mod tabbar;
+pub mod active;
+pub mod detail;
+pub mod glyphs;
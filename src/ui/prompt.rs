@@ -0,0 +1,195 @@
+use std::{fmt, rc::Rc};
+
+use crossterm::event::KeyCode;
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Direction, Layout, Rect},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph, Widget, Wrap},
+};
+
+use crate::{action::Action, event::Event};
+
+type Submit = Rc<dyn Fn(&str) -> Vec<Action>>;
+
+use super::style::SharedTheme;
+
+#[derive(Debug, Clone)]
+pub struct Choice {
+    pub key: char,
+    pub label: String,
+    pub actions: Vec<Action>,
+}
+
+impl Choice {
+
+    pub fn new(key: char, label: &str, actions: Vec<Action>) -> Choice {
+        Choice {
+            key,
+            label: label.to_string(),
+            actions,
+        }
+    }
+
+}
+
+/// Text typed into a prompt, turned into actions once it's submitted
+#[derive(Clone)]
+pub struct Input {
+    pub text: String,
+    submit: Submit,
+}
+
+impl fmt::Debug for Input {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Input").field("text", &self.text).finish_non_exhaustive()
+    }
+}
+
+/// A popup that blocks everything else until one of its choices is picked, or its input is
+/// submitted with Enter. Escape closes it without doing anything.
+#[derive(Debug, Clone)]
+pub struct Prompt {
+    pub title: String,
+    pub message: String,
+    pub choices: Vec<Choice>,
+    pub input: Option<Input>,
+}
+
+pub enum PromptResult {
+    Open,
+    Closed(Vec<Action>),
+}
+
+impl Prompt {
+
+    pub fn new(title: &str, message: String, choices: Vec<Choice>) -> Prompt {
+        Prompt {
+            title: title.to_string(),
+            message,
+            choices,
+            input: None,
+        }
+    }
+
+    /// Asks for a line of text. Nothing happens if it's left empty.
+    pub fn input<F: Fn(&str) -> Vec<Action> + 'static>(title: &str, message: String, submit: F) -> Prompt {
+        let mut prompt = Prompt::new(title, message, vec![]);
+        prompt.input = Some(Input {
+            text: String::new(),
+            submit: Rc::new(submit),
+        });
+        prompt
+    }
+
+    /// Yes/no prompt
+    pub fn confirm(title: &str, message: String, actions: Vec<Action>) -> Prompt {
+        Prompt::new(title, message, vec![
+            Choice::new('y', "Yes", actions),
+            Choice::new('n', "No", vec![]),
+        ])
+    }
+
+    pub fn error(message: String) -> Prompt {
+        Prompt::new("Error", message, vec![Choice::new('o', "Ok", vec![])])
+    }
+
+    pub fn event(&mut self, event: Event) -> PromptResult {
+        let Event::Key(k) = event else {
+            return PromptResult::Open;
+        };
+        if let Some(input) = &mut self.input {
+            return match k.code {
+                KeyCode::Esc => PromptResult::Closed(vec![]),
+                KeyCode::Enter if input.text.trim().is_empty() => PromptResult::Closed(vec![]),
+                KeyCode::Enter => PromptResult::Closed((input.submit)(input.text.trim())),
+                KeyCode::Backspace => {
+                    input.text.pop();
+                    PromptResult::Open
+                },
+                KeyCode::Char(c) => {
+                    input.text.push(c);
+                    PromptResult::Open
+                },
+                _ => PromptResult::Open,
+            };
+        }
+        match k.code {
+            KeyCode::Esc => PromptResult::Closed(vec![]),
+            KeyCode::Enter if self.choices.len() == 1 => PromptResult::Closed(self.choices.remove(0).actions),
+            KeyCode::Char(c) => {
+                match self.choices.iter().position(|choice| choice.key == c) {
+                    Some(i) => PromptResult::Closed(self.choices.remove(i).actions),
+                    None => PromptResult::Open,
+                }
+            }
+            _ => PromptResult::Open,
+        }
+    }
+
+    pub fn render(&self, area: Rect, buf: &mut Buffer, theme: SharedTheme) {
+        let width = area.width.min(60);
+        let choices: Vec<Span> = match &self.input {
+            Some(_) => vec![
+                Span::styled("[Enter]", theme.marker()),
+                Span::styled(" Save  ", theme.text()),
+                Span::styled("[Esc]", theme.marker()),
+                Span::styled(" Cancel", theme.text()),
+            ],
+            None => self.choices.iter()
+                .flat_map(|c| vec![
+                    Span::styled(format!("[{}]", c.key), theme.marker()),
+                    Span::styled(format!(" {}  ", c.label), theme.text()),
+                ])
+                .collect(),
+        };
+        let message = Paragraph::new(self.message.clone())
+            .style(theme.text())
+            .wrap(Wrap { trim: true });
+        // Close enough, wrapping on words can take a few more lines
+        let text_width = width.saturating_sub(2).max(1) as usize;
+        let lines: usize = self.message.lines().map(|l| l.chars().count().max(1).div_ceil(text_width)).sum();
+        // Borders, a gap and the choice line, and the input
+        let input_height = if self.input.is_some() { 2 } else { 0 };
+        let height = (lines as u16 + 4 + input_height).min(area.height);
+
+        let popup = centered(area, width, height);
+        Clear.render(popup, buf);
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_set(theme.glyphs().border)
+            .border_style(theme.border())
+            .title(Span::styled(format!(" {} ", self.title), theme.text()));
+        let inner = block.inner(popup);
+        block.render(popup, buf);
+
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Fill(1), Constraint::Length(input_height), Constraint::Length(1)])
+            .split(inner);
+        message.render(layout[0], buf);
+        if let Some(input) = &self.input {
+            // Keep the end of the text, where the typing happens, in view
+            let width = inner.width.saturating_sub(3) as usize;
+            let skip = input.text.chars().count().saturating_sub(width);
+            let text: String = input.text.chars().skip(skip).collect();
+            let line = Line::from(vec![
+                Span::styled("> ", theme.marker()),
+                Span::styled(text, theme.text()),
+                Span::styled(theme.glyphs().caret, theme.marker()),
+            ]);
+            buf.set_line(layout[1].x, layout[1].y, &line, layout[1].width);
+        }
+        Paragraph::new(Line::from(choices)).render(layout[2], buf);
+    }
+
+}
+
+fn centered(area: Rect, width: u16, height: u16) -> Rect {
+    Rect::new(
+        area.x + (area.width.saturating_sub(width)) / 2,
+        area.y + (area.height.saturating_sub(height)) / 2,
+        width,
+        height,
+    )
+}
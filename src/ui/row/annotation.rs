@@ -0,0 +1,59 @@
+use ratatui::{buffer::Buffer, layout::Rect, text::{Line, Span}};
+use uuid::Uuid;
+
+use crate::{data, ui::tasklist::TableColumn};
+
+use super::RenderContext;
+
+/// An annotation, under the task it belongs to
+#[derive(Debug, Clone)]
+pub struct AnnotationRow {
+    pub task: Uuid,
+    /// Position in the task's annotations
+    pub annotation: usize,
+    pub depth: usize,
+    /// Whether this and each parent is the last of its siblings
+    pub last: Vec<bool>,
+    pub idx: usize,
+}
+
+impl AnnotationRow {
+
+    pub fn render(
+        &self,
+        area: Rect,
+        buf: &mut Buffer,
+        context: RenderContext,
+    ) -> u16 {
+        let row_area = Rect::new(
+            area.x,
+            area.y + context.y,
+            area.width,
+            1,
+        );
+        let idx = context.index + 1;
+        if context.list.cursor == idx - 1 {
+            buf.set_style(row_area, context.theme.cursor());
+        }
+        if idx <= context.list.focus {
+            return 0;
+        }
+        let Some(annotation) = context.task_map.get(&self.task).and_then(|t| t.annotations.get(self.annotation)) else {
+            return 0;
+        };
+        let Some((_, c_x, width)) = context.widths.iter().find(|(c, _, _)| matches!(c, TableColumn::Description)) else {
+            return 0;
+        };
+        let line = Line::from(vec![
+            Span::styled(context.theme.glyphs().annotation, context.theme.fold()),
+            Span::styled(format!("{} ", data::format_relative(annotation.entry, context.list.now)), context.theme.fold()),
+            Span::styled(annotation.description.clone(), context.theme.text()),
+        ]);
+        let depth = u16::try_from(self.depth * 2).unwrap();
+        let guide = super::guide(&self.last, context.list.config.guides, context.theme.glyphs(), true);
+        buf.set_stringn(row_area.x + c_x, row_area.y, guide, *width as usize, context.theme.border());
+        buf.set_line(row_area.x + c_x + depth, row_area.y, &line, width.saturating_sub(depth));
+        1
+    }
+
+}
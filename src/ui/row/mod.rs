@@ -1,19 +1,16 @@
 use std::collections::HashMap;
 
-use crate::{data::Task, tabs::list::List};
+use crate::{config::Guides, data::Task, tabs::list::List, taskrc::UdaSchema};
 
-use self::{task::TaskRow, text::TextRow};
-use ratatui::{buffer::Buffer, layout::Rect};
+use self::{annotation::AnnotationRow, task::TaskRow, text::TextRow};
+use ratatui::{buffer::Buffer, layout::Rect, text::{Line, Span}};
 use uuid::Uuid;
 
-use super::{style::SharedTheme, tasklist::TableColumn};
+use super::{glyphs::{Glyphs, ASCII}, style::Theme, tasklist::TableColumn};
 
 pub mod text;
 pub mod task;
-
-// TODO: Bake this into config
-pub const FOLD_OPEN: &str = " ";
-pub const FOLD_CLOSE: &str = " ";
+pub mod annotation;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum FoldState {
@@ -35,6 +32,9 @@ pub fn render_row(
         RowEntry::Text(t) => {
             t.render(area, buf, context)
         },
+        RowEntry::Annotation(a) => {
+            a.render(area, buf, context)
+        },
     }
 }
 
@@ -42,6 +42,7 @@ pub fn render_row(
 pub enum RowEntry {
     Text(TextRow),
     Task(TaskRow),
+    Annotation(AnnotationRow),
 }
 
 impl RowEntry {
@@ -50,6 +51,7 @@ impl RowEntry {
         match self {
             RowEntry::Task(t) => t.fold_state.clone(),
             RowEntry::Text(t) => t.fold_state.clone(),
+            RowEntry::Annotation(_) => FoldState::NoChildren,
         }
     }
 
@@ -57,18 +59,155 @@ impl RowEntry {
         match self {
             RowEntry::Task(t) => t.idx,
             RowEntry::Text(t) => t.idx,
+            RowEntry::Annotation(a) => a.idx,
+        }
+    }
+
+    /// Lines the row takes up when the description column is `width` wide
+    pub fn height(&self, list: &List, tasks: &HashMap<Uuid, Task>, width: u16) -> usize {
+        match self {
+            RowEntry::Task(t) => {
+                let Some(task) = tasks.get(&t.task) else {
+                    return 1;
+                };
+                let depth = u16::try_from(t.depth * 2).unwrap_or(u16::MAX);
+                t.description_lines(task, list, &list.theme, width.saturating_sub(depth)).len().max(1)
+            },
+            RowEntry::Text(_) | RowEntry::Annotation(_) => 1,
+        }
+    }
+
+    /// Column and line of the fold glyph within the row, if it has one that's visible
+    pub fn fold_position(&self, list: &List, tasks: &HashMap<Uuid, Task>, theme: &Theme, widths: &[(TableColumn, u16, u16)]) -> Option<(u16, u16)> {
+        match self {
+            RowEntry::Task(t) => {
+                let task = tasks.get(&t.task)?;
+                let (_, c_x, width) = widths.iter().find(|(c, _, _)| matches!(c, TableColumn::Description))?;
+                let depth = u16::try_from(t.depth * 2).ok()?;
+                let (x, y) = t.fold_position(task, list, theme, width.checked_sub(depth)?)?;
+                Some((c_x + depth + x, y))
+            },
+            RowEntry::Text(t) => {
+                let glyph = match t.fold_state {
+                    FoldState::NoChildren => return None,
+                    FoldState::Folded => theme.glyphs().fold_close,
+                    FoldState::Open => theme.glyphs().fold_open,
+                };
+                let x = Span::raw(t.text.as_str()).width() + Span::raw(glyph).width() - 1;
+                Some((u16::try_from(x).ok()?, 0))
+            },
+            RowEntry::Annotation(_) => None,
         }
     }
     
 }
 
+/// Connectors drawn in the indentation of a row, two columns per level. `last` has whether
+/// the row and each of its parents are the last of their siblings, from the top down. Lines
+/// after the `first` of a row only continue the lines down.
+pub fn guide(last: &[bool], guides: Guides, glyphs: &Glyphs, first: bool) -> String {
+    let [branch, end, down] = match guides {
+        Guides::Off => return String::new(),
+        Guides::Lines => glyphs.guides,
+        Guides::Ascii => ASCII.guides,
+    };
+    let Some((own, parents)) = last.split_last() else {
+        return String::new();
+    };
+    let mut guide: String = parents.iter().map(|l| if *l { "  " } else { down }).collect();
+    guide.push_str(match (first, own) {
+        (true, false) => branch,
+        (true, true) => end,
+        (false, false) => down,
+        (false, true) => "  ",
+    });
+    guide
+}
+
+/// Word wraps styled spans to `width`, words longer than a line get split
+pub fn wrap(spans: Vec<Span<'_>>, width: usize) -> Vec<Line<'_>> {
+    let mut lines = vec![];
+    let mut line: Vec<Span> = vec![];
+    let mut used = 0;
+    for span in spans {
+        for word in span.content.split_inclusive(' ') {
+            let word_width = Span::raw(word).width();
+            let fits = Span::raw(word.trim_end()).width();
+            if used > 0 && used + fits > width {
+                lines.push(Line::from(std::mem::take(&mut line)));
+                used = 0;
+            }
+            if used == 0 && fits > width && width > 0 {
+                // Doesn't fit on a line of its own either
+                let mut chunk = String::new();
+                for c in word.chars() {
+                    if Span::raw(chunk.as_str()).width() + Span::raw(c.to_string()).width() > width {
+                        lines.push(Line::from(Span::styled(std::mem::take(&mut chunk), span.style)));
+                    }
+                    chunk.push(c);
+                }
+                used = Span::raw(chunk.as_str()).width();
+                line.push(Span::styled(chunk, span.style));
+                continue;
+            }
+            used += word_width;
+            line.push(Span::styled(word.to_string(), span.style));
+        }
+    }
+    if !line.is_empty() || lines.is_empty() {
+        lines.push(Line::from(line));
+    }
+    lines
+}
+
 
 pub struct RenderContext<'a> {
     pub y: u16, 
     pub depth: u16, 
-    pub theme: SharedTheme, 
+    pub theme: &'a Theme,
     pub widths: &'a Vec<(TableColumn, u16, u16)>,
     pub list: &'a List,
     pub index: usize,
     pub task_map: &'a HashMap<Uuid, Task>,
+    pub udas: &'a UdaSchema,
+}
+
+
+#[cfg(test)]
+mod tests {
+    use ratatui::style::{Style, Stylize};
+
+    use super::*;
+
+    fn text(line: &Line) -> String {
+        line.spans.iter().map(|s| s.content.as_ref()).collect()
+    }
+
+    #[test]
+    fn guides() {
+        let glyphs = &crate::ui::glyphs::UNICODE;
+        assert_eq!(guide(&[], Guides::Lines, glyphs, true), "");
+        assert_eq!(guide(&[false], Guides::Lines, glyphs, true), "├─");
+        assert_eq!(guide(&[false, true], Guides::Lines, glyphs, true), "│ └─");
+        assert_eq!(guide(&[true, false], Guides::Lines, glyphs, false), "  │ ");
+        assert_eq!(guide(&[false, true], Guides::Ascii, glyphs, true), "| `-");
+        assert_eq!(guide(&[false, true], Guides::Lines, &ASCII, true), "| `-");
+        assert_eq!(guide(&[false, true], Guides::Off, glyphs, true), "");
+    }
+
+    #[test]
+    fn wrap_words() {
+        let bold = Style::default().bold();
+        let spans = vec![Span::styled("▶ ", bold), Span::raw("write the release notes"), Span::raw(" 1:02:03")];
+        let lines = wrap(spans, 12);
+        let texts: Vec<String> = lines.iter().map(text).collect();
+        assert_eq!(texts, vec!["▶ write the ", "release ", "notes ", "1:02:03"]);
+        assert_eq!(lines[0].spans[0].style, bold);
+
+        let lines = wrap(vec![Span::raw("abcdefghij")], 4);
+        let texts: Vec<String> = lines.iter().map(text).collect();
+        assert_eq!(texts, vec!["abcd", "efgh", "ij"]);
+
+        assert_eq!(wrap(vec![], 10).len(), 1);
+    }
 }
@@ -1,23 +1,168 @@
 use std::cmp::max;
 
-use ratatui::{buffer::Buffer, layout::Rect, style::{Color, Style}, text::{Line, Span, Text}};
+use chrono::NaiveDateTime;
+
+use ratatui::{buffer::Buffer, layout::Rect, style::{Modifier, Style}, text::{Line, Span, Text}};
 use uuid::Uuid;
 
-use crate::{data::TaskStatus, ui::tasklist::TableColumn};
+use crate::{data::{self, Task, TaskStatus}, tabs::list::List, ui::{glyphs::Glyphs, style::Theme, tasklist::TableColumn}};
+
+use super::{FoldState, RenderContext};
 
-use super::{FoldState, RenderContext, FOLD_CLOSE, FOLD_OPEN};
+const PROGRESS_WIDTH: usize = 8;
 
 #[derive(Debug, Clone)]
 pub struct TaskRow {
     pub task: Uuid,
     pub fold_state: FoldState,
     pub depth: usize,
+    /// Whether this and each parent is the last of its siblings
+    pub last: Vec<bool>,
     pub idx: usize,
+    pub progress: Option<Progress>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Progress {
+    pub done: usize,
+    pub total: usize,
+}
+
+impl Progress {
+
+    fn spans<'a>(&self, bar: bool, glyphs: &Glyphs, style: Style) -> Vec<Span<'a>> {
+        let mut spans = vec![Span::styled(format!(" {}/{}", self.done, self.total), style)];
+        if bar {
+            let filled = self.done * PROGRESS_WIDTH / self.total;
+            let bar = format!(" {}{}", glyphs.progress_done.repeat(filled), glyphs.progress_left.repeat(PROGRESS_WIDTH - filled));
+            spans.push(Span::styled(bar, style));
+        }
+        spans
+    }
+
 }
 
 
+/// Text style of a task, marked when it's due soon or started
+pub fn description_style(task: &Task, theme: &Theme, now: NaiveDateTime) -> Style {
+    let mut style = theme.text();
+    if !task.is_resolved() {
+        match task.days_until_due(now) {
+            Some(days) if days < 0 => style = style.patch(theme.overdue()),
+            Some(0) => style = style.patch(theme.due_today()),
+            _ => {},
+        }
+    }
+    if task.start.is_some() {
+        style = style.patch(theme.active());
+    }
+    style
+}
+
 impl TaskRow {
 
+    /// The whole row is in the taskrc colors if they're used and any apply. Their background is
+    /// left out on the cursor and the critical path, so those still show.
+    fn row_style(&self, task: &Task, list: &List, theme: &Theme) -> Option<Style> {
+        let dependencies = &list.dependencies;
+        let mut style = list.colors.as_ref()?
+            .style(task, list.now, dependencies.blocked.contains(&self.task), dependencies.blocking.contains(&self.task))?;
+        if list.selected_task() == Some(self.task) || list.critical_path.contains(&self.task) {
+            style.bg = None;
+        }
+        Some(theme.text().patch(style))
+    }
+
+    fn description_style(&self, task: &Task, list: &List, theme: &Theme) -> Style {
+        self.row_style(task, list, theme).unwrap_or_else(|| description_style(task, theme, list.now))
+    }
+
+    /// Markers, the description and everything that goes after it, and which of them is the
+    /// fold glyph
+    fn description_spans<'a>(&self, task: &'a Task, list: &List, theme: &Theme) -> (Vec<Span<'a>>, Option<usize>) {
+        let mut spans = vec![];
+        let mut fold = None;
+        let dependencies = &list.dependencies;
+        let glyphs = theme.glyphs();
+        if task.start.is_some() {
+            spans.push(Span::styled(glyphs.active, theme.marker()));
+        }
+        if dependencies.in_cycle(&self.task) {
+            spans.push(Span::styled(glyphs.cycle, theme.marker()));
+        } else if dependencies.blocked.contains(&self.task) {
+            spans.push(Span::styled(glyphs.blocked, theme.marker()));
+        } else if dependencies.blocking.contains(&self.task) {
+            spans.push(Span::styled(glyphs.blocking, theme.marker()));
+        }
+        spans.push(
+            Span::styled(task.description.as_str(), self.description_style(task, list, theme)),
+        );
+        if let Some(recur) = &task.recur {
+            spans.push(Span::styled(format!("{}{}", glyphs.recur, recur), theme.fold()));
+        }
+        match self.fold_state {
+            FoldState::NoChildren => {},
+            FoldState::Folded => {
+                fold = Some(spans.len());
+                let span: Span = glyphs.fold_close.into();
+                spans.push(span);
+            }
+            FoldState::Open => {
+                fold = Some(spans.len());
+                let span: Span = glyphs.fold_open.into();
+                spans.push(span);
+            },
+        }
+        if let Some(progress) = self.progress {
+            spans.extend(progress.spans(list.config.progress_bar, glyphs, theme.fold()));
+        }
+        if let Some(elapsed) = task.elapsed(list.now) {
+            spans.push(Span::styled(format!(" {}", data::format_elapsed(elapsed)), theme.marker()));
+        }
+        (spans, fold)
+    }
+
+    fn wrap_spans<'a>(spans: Vec<Span<'a>>, list: &List, width: u16) -> Vec<Line<'a>> {
+        if list.config.wrap {
+            super::wrap(spans, width as usize)
+        } else {
+            vec![Line::from(spans)]
+        }
+    }
+
+    /// The description spans wrapped to `width` when wrapping is on, with the first annotation
+    /// under them when that's on.
+    pub fn description_lines<'a>(&self, task: &'a Task, list: &List, theme: &Theme, width: u16) -> Vec<Line<'a>> {
+        let (spans, _) = self.description_spans(task, list, theme);
+        let mut lines = TaskRow::wrap_spans(spans, list, width);
+        if list.config.annotation_line {
+            if let Some(annotation) = task.annotations.first() {
+                let style = theme.fold().add_modifier(Modifier::DIM);
+                lines.push(Line::styled(annotation.description.as_str(), style));
+            }
+        }
+        lines
+    }
+
+    /// Column and line of the fold glyph within the description, if it's visible
+    pub fn fold_position(&self, task: &Task, list: &List, theme: &Theme, width: u16) -> Option<(u16, u16)> {
+        let (spans, fold) = self.description_spans(task, list, theme);
+        let fold = fold?;
+        // The glyph itself is at the end of its span, after a space
+        let offset = spans[..=fold].iter().map(|s| s.width()).sum::<usize>().checked_sub(1)?;
+        // Wrapping keeps every character, so counting along the lines finds it again
+        let mut start = 0;
+        for (y, line) in TaskRow::wrap_spans(spans, list, width).iter().enumerate() {
+            let end = start + line.width();
+            if offset < end {
+                let x = offset - start;
+                return (x < width as usize).then_some((x as u16, y as u16));
+            }
+            start = end;
+        }
+        None
+    }
+
     pub fn render(
         &self, 
         area: Rect, 
@@ -31,44 +176,63 @@ impl TaskRow {
             1,
         );
         let mut y_max = 0;
-        let mut idx = context.index + 1;
+        let idx = context.index + 1;
+        let task = context.task_map.get(&self.task).unwrap();
+        let depth: u16 = u16::try_from(self.depth * 2).unwrap();
+        let description = context.widths.iter()
+            .find(|(c, _, _)| matches!(c, TableColumn::Description))
+            .map(|(_, _, width)| self.description_lines(task, context.list, context.theme, width.saturating_sub(depth)))
+            .unwrap_or_default();
+        // Wrapped rows are highlighted all the way down
+        let height = (description.len().max(1) as u16).min(area.height.saturating_sub(context.y));
+        let highlight = Rect { height, ..row_area };
+        let row_style = self.row_style(task, context.list, context.theme);
+        if let Some(style) = row_style {
+            buf.set_style(highlight, style);
+        }
         if context.list.cursor == idx - 1 {
-            buf.set_style(row_area, context.theme.cursor());
+            buf.set_style(highlight, context.theme.cursor());
+        } else if context.list.critical_path.contains(&self.task) {
+            buf.set_style(highlight, context.theme.highlight());
         }
         if idx > context.list.focus {
-            let task = context.task_map.get(&self.task).unwrap();
-            for (column, c_x, _width) in context.widths {
+            for (column, c_x, width) in context.widths {
                 match column {
                     TableColumn::Description => {
                         let mut y_offset = 0;
-                        let mut lines = vec![];
-                        lines.push(
-                            Span::styled(&task.description, context.theme.text()),
-                        );
-                        match self.fold_state {
-                            FoldState::NoChildren => {},
-                            FoldState::Folded => {
-                                let span: Span = FOLD_CLOSE.into();
-                                lines.push(span);
-                            }
-                            FoldState::Open => {
-                                let span: Span = FOLD_OPEN.into();
-                                lines.push(span);
-                            },
-                        }
-                        let text: Text = Line::from(lines).into();
-                        for line in &text.lines {
+                        for line in &description {
                             if context.y + y_offset >= area.height {
                                 return max(y_max, y_offset);
                             }
-                            let depth: u16 = u16::try_from(self.depth * 2).unwrap();
-                            buf.set_line(row_area.x + c_x + depth, row_area.y + y_offset, line, row_area.width);
+                            let guide = super::guide(&self.last, context.list.config.guides, context.theme.glyphs(), y_offset == 0);
+                            buf.set_stringn(row_area.x + c_x, row_area.y + y_offset, guide, *width as usize, context.theme.border());
+                            buf.set_line(row_area.x + c_x + depth, row_area.y + y_offset, line, width.saturating_sub(depth));
                             y_offset += 1;
                         };
                         y_max = max(y_offset, y_max);
                     },
+                    TableColumn::Tracked => {
+                        let Some(tracked) = context.list.tracked.borrow().total(&self.task) else {
+                            continue;
+                        };
+                        let line = Line::styled(data::format_elapsed(tracked), row_style.unwrap_or_else(|| context.theme.fold()));
+                        buf.set_line(row_area.x + c_x, row_area.y, &line, *width);
+                        y_max = max(1, y_max);
+                    },
+                    TableColumn::Uda(name) => {
+                        let Some(value) = task.uda(context.udas, name) else {
+                            continue;
+                        };
+                        let line = Line::styled(value.to_string(), row_style.unwrap_or_else(|| context.theme.text()));
+                        buf.set_line(row_area.x + c_x, row_area.y, &line, *width);
+                        y_max = max(1, y_max);
+                    },
                     TableColumn::State => {
-                        let (sequence, style) = task.status.get_display(task);
+                        let sequence = task.status.get_display(task, context.theme.glyphs());
+                        let style = row_style.unwrap_or_else(|| match task.status {
+                            TaskStatus::Pending => context.theme.urgency(task.urgency_level()),
+                            status => context.theme.status(&status),
+                        });
                         let span: Span = Span::styled(sequence.clone(), style);
                         let text: Text = span.into();
                         let mut y_offset = 0;
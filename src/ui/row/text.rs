@@ -1,7 +1,7 @@
 use super::{FoldState, RenderContext, RowEntry};
 use ratatui::{buffer::Buffer, layout::Rect, text::{Line, Span, Text}};
 
-use super::{render_row, FOLD_CLOSE, FOLD_OPEN};
+use super::render_row;
 
 
 
@@ -35,16 +35,16 @@ impl TextRow {
         }
         let mut y_max = 0;
         let mut text_parts = vec![];
-        let span: Span = self.text.clone().into();
+        let span = Span::styled(self.text.clone(), context.theme.header());
         text_parts.push(span.clone());
         match self.fold_state {
             FoldState::NoChildren => {},
             FoldState::Folded => {
-                let span: Span = FOLD_CLOSE.into();
+                let span: Span = context.theme.glyphs().fold_close.into();
                 text_parts.push(span);
             }
             FoldState::Open => {
-                let span: Span = FOLD_OPEN.into();
+                let span: Span = context.theme.glyphs().fold_open.into();
                 text_parts.push(span);
             },
         }
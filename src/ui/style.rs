@@ -2,9 +2,13 @@
 
 use std::rc::Rc;
 
-use ratatui::style::{Color, Style};
+use ratatui::style::{Color, Modifier, Style};
 use serde::{Deserialize, Serialize};
-use color_eyre::Result;
+use color_eyre::{eyre::eyre, Result};
+
+use crate::{config::Config, data::TaskStatus};
+
+use super::glyphs::Glyphs;
 
 pub type SharedTheme = Rc<Theme>;
 
@@ -15,69 +19,324 @@ mod color_parser {
 
     use super::*;
 
-    pub fn serialize<S>(c: &Color, serializer: S) -> Result<S::Ok, S::Error> 
-    where 
+    pub fn serialize<S>(c: &Option<Color>, serializer: S) -> Result<S::Ok, S::Error>
+    where
         S: Serializer
     {
-        let s = c.to_string();
-        String::serialize(&s, serializer)
+        let s = c.map(|c| c.to_string());
+        Option::<String>::serialize(&s, serializer)
     }
 
-    pub fn deserialize<'de, D>(deserializer: D) -> Result<Color, D::Error>
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<Color>, D::Error>
     where
         D: Deserializer<'de>,
     {
-        let s: String = String::deserialize(deserializer)?;
-        Color::from_str(&s).map_err(serde::de::Error::custom)
+        let s: Option<String> = Option::deserialize(deserializer)?;
+        s.map(|s| Color::from_str(&s).map_err(serde::de::Error::custom)).transpose()
     }
-    
+
+}
+
+/// A style as it's written in a theme file, e.g. `{"fg": "#fab387", "bold": true}`
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default)]
+#[serde(default)]
+pub struct StyleDef {
+
+    #[serde(with = "color_parser", skip_serializing_if = "Option::is_none")]
+    fg: Option<Color>,
+
+    #[serde(with = "color_parser", skip_serializing_if = "Option::is_none")]
+    bg: Option<Color>,
+
+    bold: bool,
+
+    italic: bool,
+
+    dim: bool,
+
+    underlined: bool,
 }
 
+impl StyleDef {
+
+    const fn fg(color: Color) -> StyleDef {
+        StyleDef { fg: Some(color), bg: None, bold: false, italic: false, dim: false, underlined: false }
+    }
+
+    const fn bg(color: Color) -> StyleDef {
+        StyleDef { fg: None, bg: Some(color), bold: false, italic: false, dim: false, underlined: false }
+    }
 
+    const fn bold(mut self) -> StyleDef {
+        self.bold = true;
+        self
+    }
+
+    pub fn style(&self) -> Style {
+        let mut style = Style::default();
+        if let Some(fg) = self.fg {
+            style = style.fg(fg);
+        }
+        if let Some(bg) = self.bg {
+            style = style.bg(bg);
+        }
+        for (on, modifier) in [
+            (self.bold, Modifier::BOLD),
+            (self.italic, Modifier::ITALIC),
+            (self.dim, Modifier::DIM),
+            (self.underlined, Modifier::UNDERLINED),
+        ] {
+            if on {
+                style = style.add_modifier(modifier);
+            }
+        }
+        style
+    }
+
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct StatusStyles {
+    pending: StyleDef,
+    waiting: StyleDef,
+    blocked: StyleDef,
+    completed: StyleDef,
+    deleted: StyleDef,
+    recurring: StyleDef,
+}
+
+/// Styles by what they're for rather than their color. Missing slots in a theme file are taken
+/// from the dark theme.
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
 pub struct Theme {
 
-    #[serde(with = "color_parser")]
-    border: Color,
+    border: StyleDef,
+
+    text: StyleDef,
+
+    fold: StyleDef,
+
+    /// The selected row, day or card
+    cursor: StyleDef,
+
+    marker: StyleDef,
+
+    /// Tasks on the critical path
+    highlight: StyleDef,
 
-    #[serde(with = "color_parser")]
-    text: Color,
+    /// Status headers, agenda days and other groups
+    header: StyleDef,
 
-    #[serde(with = "color_parser")]
-    fold: Color,
+    statuses: StatusStyles,
 
-    #[serde(with = "color_parser")]
-    cursor: Color,
+    /// Pending tasks from least to most urgent
+    urgency: [StyleDef; 3],
+
+    overdue: StyleDef,
+
+    due_today: StyleDef,
+
+    /// Started tasks
+    active: StyleDef,
+
+    tag: StyleDef,
+
+    project: StyleDef,
+
+    /// Picked in the config rather than the theme
+    #[serde(skip)]
+    glyphs: Glyphs,
 }
 
 impl Theme {
 
+    /// A theme file in `themes/` of the config directory, otherwise a bundled theme
+    pub fn load(name: &str) -> Result<Theme> {
+        if let Some(path) = Config::dir().map(|d| d.join("themes").join(format!("{}.json", name))) {
+            if path.exists() {
+                let contents = std::fs::read_to_string(path)?;
+                return Ok(serde_json::from_str(&contents)?);
+            }
+        }
+        match name {
+            "dark" => Ok(Theme::dark()),
+            "light" => Ok(Theme::light()),
+            _ => Err(eyre!("No theme called \"{}\", the bundled ones are dark and light", name)),
+        }
+    }
+
+    /// Catppuccin Mocha
+    pub fn dark() -> Theme {
+        let blue = StyleDef::fg(Color::Blue);
+        Theme {
+            border: StyleDef::fg(Color::Rgb(49, 50, 68)),
+            text: StyleDef::fg(Color::Rgb(205, 214, 244)),
+            fold: StyleDef::fg(Color::Rgb(205, 214, 244)),
+            cursor: StyleDef::bg(Color::Rgb(69, 71, 90)),
+            marker: StyleDef::fg(Color::Rgb(250, 179, 135)),
+            highlight: StyleDef::bg(Color::Rgb(88, 56, 66)),
+            header: StyleDef::fg(Color::Rgb(205, 214, 244)).bold(),
+            statuses: StatusStyles {
+                pending: StyleDef::fg(Color::Red),
+                waiting: blue,
+                blocked: blue,
+                completed: blue,
+                deleted: StyleDef::fg(Color::Gray),
+                recurring: blue,
+            },
+            urgency: [StyleDef::fg(Color::Red), StyleDef::fg(Color::Red), StyleDef::fg(Color::Red).bold()],
+            overdue: StyleDef::fg(Color::Rgb(243, 139, 168)),
+            due_today: StyleDef::fg(Color::Rgb(249, 226, 175)),
+            active: StyleDef::fg(Color::Rgb(166, 227, 161)),
+            tag: StyleDef::fg(Color::Rgb(137, 180, 250)),
+            project: StyleDef::fg(Color::Rgb(203, 166, 247)),
+            glyphs: Glyphs::default(),
+        }
+    }
+
+    /// Catppuccin Latte
+    pub fn light() -> Theme {
+        let blue = StyleDef::fg(Color::Rgb(30, 102, 245));
+        let red = StyleDef::fg(Color::Rgb(210, 15, 57));
+        Theme {
+            border: StyleDef::fg(Color::Rgb(188, 192, 204)),
+            text: StyleDef::fg(Color::Rgb(76, 79, 105)),
+            fold: StyleDef::fg(Color::Rgb(108, 111, 133)),
+            cursor: StyleDef::bg(Color::Rgb(204, 208, 218)),
+            marker: StyleDef::fg(Color::Rgb(254, 100, 11)),
+            highlight: StyleDef::bg(Color::Rgb(240, 212, 220)),
+            header: StyleDef::fg(Color::Rgb(76, 79, 105)).bold(),
+            statuses: StatusStyles {
+                pending: red,
+                waiting: blue,
+                blocked: blue,
+                completed: blue,
+                deleted: StyleDef::fg(Color::Rgb(140, 143, 161)),
+                recurring: blue,
+            },
+            urgency: [red, red, red.bold()],
+            overdue: red,
+            due_today: StyleDef::fg(Color::Rgb(223, 142, 29)),
+            active: StyleDef::fg(Color::Rgb(64, 160, 43)),
+            tag: blue,
+            project: StyleDef::fg(Color::Rgb(136, 57, 239)),
+            glyphs: Glyphs::default(),
+        }
+    }
+
     pub fn border(&self) -> Style {
-        Style::default().fg(self.border)
+        self.border.style()
     }
 
     pub fn text(&self) -> Style {
-        Style::default().fg(self.text)
+        self.text.style()
     }
 
     pub fn fold(&self) -> Style {
-        Style::default().fg(self.fold)
+        self.fold.style()
     }
 
     pub fn cursor(&self) -> Style {
-        Style::default().bg(self.cursor)
+        self.cursor.style()
+    }
+
+    pub fn marker(&self) -> Style {
+        self.marker.style()
+    }
+
+    pub fn highlight(&self) -> Style {
+        self.highlight.style()
+    }
+
+    pub fn header(&self) -> Style {
+        self.header.style()
+    }
+
+    pub fn status(&self, status: &TaskStatus) -> Style {
+        let statuses = &self.statuses;
+        match status {
+            TaskStatus::Pending => statuses.pending,
+            TaskStatus::Waiting => statuses.waiting,
+            TaskStatus::Blocked => statuses.blocked,
+            TaskStatus::Completed => statuses.completed,
+            TaskStatus::Deleted => statuses.deleted,
+            TaskStatus::Recurring => statuses.recurring,
+        }.style()
+    }
+
+    /// Levels start at 1, past the last one is the same as the last
+    pub fn urgency(&self, level: usize) -> Style {
+        self.urgency[level.clamp(1, self.urgency.len()) - 1].style()
+    }
+
+    pub fn overdue(&self) -> Style {
+        self.overdue.style()
+    }
+
+    pub fn due_today(&self) -> Style {
+        self.due_today.style()
+    }
+
+    pub fn active(&self) -> Style {
+        self.active.style()
+    }
+
+    pub fn tag(&self) -> Style {
+        self.tag.style()
+    }
+
+    pub fn project(&self) -> Style {
+        self.project.style()
+    }
+
+    pub fn glyphs(&self) -> &Glyphs {
+        &self.glyphs
+    }
+
+    #[must_use = "method moves the value of self and returns the modified value"]
+    pub fn with_glyphs(mut self, glyphs: Glyphs) -> Self {
+        self.glyphs = glyphs;
+        self
+    }
+
+}
+
+impl Default for StatusStyles {
+    fn default() -> Self {
+        Theme::dark().statuses
     }
-    
 }
 
 impl Default for Theme {
     fn default() -> Self {
-        Theme { 
-            border: Color::Rgb(49, 50, 68),
-            text: Color::Rgb(205, 214, 244),
-            fold: Color::Rgb(205, 214, 244),
-            cursor: Color::Rgb(69, 71, 90),
-        }
+        Theme::dark()
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn partial_theme_file() {
+        let theme: Theme = serde_json::from_str(r##"{
+            "text": {"fg": "#ffffff", "italic": true},
+            "statuses": {"waiting": {"fg": "yellow", "dim": true}},
+            "urgency": [{"fg": "green"}, {"fg": "yellow"}, {"fg": "red", "bold": true, "underlined": true}]
+        }"##).unwrap();
+        assert_eq!(theme.text(), Style::default().fg(Color::Rgb(255, 255, 255)).add_modifier(Modifier::ITALIC));
+        assert_eq!(theme.status(&TaskStatus::Waiting), Style::default().fg(Color::Yellow).add_modifier(Modifier::DIM));
+        assert_eq!(theme.urgency(0), Style::default().fg(Color::Green));
+        assert_eq!(theme.urgency(5), Style::default().fg(Color::Red).add_modifier(Modifier::BOLD | Modifier::UNDERLINED));
+        // The rest is from the dark theme
+        assert_eq!(theme.status(&TaskStatus::Deleted), Theme::dark().status(&TaskStatus::Deleted));
+        assert_eq!(theme.border(), Theme::dark().border());
+
+        let written = serde_json::to_string(&Theme::light()).unwrap();
+        let read: Theme = serde_json::from_str(&written).unwrap();
+        assert_eq!(read.header(), Theme::light().header());
+    }
+}
@@ -0,0 +1,51 @@
+use ratatui::{buffer::Buffer, layout::Rect, text::{Line, Span}};
+
+use super::style::SharedTheme;
+
+/// Titles of every tab along the top, numbered by the key that opens them.
+pub struct TabBar<'a> {
+    titles: &'a [String],
+    active: usize,
+    theme: SharedTheme,
+}
+
+impl<'a> TabBar<'a> {
+
+    pub fn new(titles: &'a [String], active: usize, theme: SharedTheme) -> TabBar<'a> {
+        TabBar { titles, active, theme }
+    }
+
+    fn label(i: usize, title: &str) -> String {
+        format!(" {} {} ", i + 1, title)
+    }
+
+    /// The tab drawn at `column` when the bar is in `area`
+    pub fn tab_at(&self, area: Rect, column: u16) -> Option<usize> {
+        let separator = Span::raw(self.theme.glyphs().separator).width();
+        let mut x = area.x as usize;
+        for (i, title) in self.titles.iter().enumerate() {
+            let width = Span::raw(TabBar::label(i, title)).width();
+            if (x..x + width).contains(&(column as usize)) {
+                return Some(i);
+            }
+            x += width + separator;
+        }
+        None
+    }
+
+    pub fn render(self, area: Rect, buf: &mut Buffer) {
+        let mut spans = vec![];
+        for (i, title) in self.titles.iter().enumerate() {
+            let style = if i == self.active {
+                self.theme.cursor()
+            } else {
+                self.theme.fold()
+            };
+            spans.push(Span::styled(TabBar::label(i, title), style));
+            spans.push(Span::styled(self.theme.glyphs().separator, self.theme.border()));
+        }
+        spans.pop();
+        buf.set_line(area.x, area.y, &Line::from(spans), area.width);
+    }
+
+}
@@ -15,7 +15,7 @@ use ratatui::{
 };
 use uuid::Uuid;
 
-use crate::{data::Task, tabs::list::List};
+use crate::{data::Task, tabs::list::List, taskrc::SharedUdas};
 
 use super::{row::RowEntry, style::SharedTheme};
 
@@ -37,22 +37,36 @@ pub struct TaskListWidget<'a> {
 
     rows: &'a [RowEntry],
 
+    columns: Vec<TableColumn>,
+
     theme: SharedTheme,
 
+    udas: SharedUdas,
+
 }
 
 impl<'a> TaskListWidget<'a> {
 
-    pub fn new(rows: &'a [RowEntry], theme: SharedTheme) -> TaskListWidget<'a> {
+    pub fn new(rows: &'a [RowEntry], theme: SharedTheme, udas: SharedUdas) -> TaskListWidget<'a> {
+        let columns = vec![TableColumn::State, TableColumn::Description];
         TaskListWidget {
             style: Default::default(),
-            widths: vec![Constraint::Length(4), Constraint::Fill(40)],
+            widths: columns.iter().map(|c| c.width()).collect(),
             block: Default::default(),
             rows,
-            theme: theme.clone()
+            columns,
+            theme: theme.clone(),
+            udas,
         }
     }
 
+    #[must_use = "method moves the value of self and returns the modified value"]
+    pub fn columns(mut self, columns: Vec<TableColumn>) -> Self {
+        self.widths = columns.iter().map(|c| c.width()).collect();
+        self.columns = columns;
+        self
+    }
+
     #[must_use = "method moves the value of self and returns the modified value"]
     pub fn block(mut self, block: Block<'a>) -> Self {
         self.block = Some(block);
@@ -61,10 +75,35 @@ impl<'a> TaskListWidget<'a> {
 
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub enum TableColumn {
     State,
     Description,
+    /// Time tracked in Timewarrior, subtasks included
+    Tracked,
+    Uda(String),
+}
+
+impl TableColumn {
+
+    pub fn from_name(name: &str) -> TableColumn {
+        match name {
+            "state" => TableColumn::State,
+            "description" => TableColumn::Description,
+            "tracked" => TableColumn::Tracked,
+            uda => TableColumn::Uda(uda.to_string()),
+        }
+    }
+
+    fn width(&self) -> Constraint {
+        match self {
+            TableColumn::State => Constraint::Length(4),
+            TableColumn::Description => Constraint::Fill(40),
+            TableColumn::Tracked => Constraint::Length(10),
+            TableColumn::Uda(_) => Constraint::Length(12),
+        }
+    }
+
 }
 
 
@@ -74,12 +113,31 @@ fn get_widths(widths: &Vec<Constraint>, columns: &[TableColumn], max_width: u16)
         .constraints(widths)
         .split(Rect::new(0, 0, max_width, 1));
     let mut column_iter = columns.iter();
-    rects.iter().map(|c| (*column_iter.next().unwrap(), c.x, c.width)).collect()
+    rects.iter().map(|c| (column_iter.next().unwrap().clone(), c.x, c.width)).collect()
+}
+
+/// Where a row was drawn, to find what the mouse is on
+#[derive(Debug, Clone, Copy)]
+pub struct RowArea {
+    pub index: usize,
+    pub area: Rect,
+    /// Position of the fold glyph on screen
+    pub fold: Option<(u16, u16)>,
 }
 
 impl TaskListWidget<'_> {
 
-    pub fn render(self, area: Rect, buf: &mut Buffer, list: &List, task_map: &HashMap<Uuid, Task>) {
+    /// Width the description column gets when the list is `width` wide
+    pub fn description_width(&self, width: u16) -> u16 {
+        let width = self.block.as_ref().map_or(width, |b| b.inner(Rect::new(0, 0, width, 1)).width);
+        get_widths(&self.widths, &self.columns, width)
+            .into_iter()
+            .find(|(c, _, _)| matches!(c, TableColumn::Description))
+            .map_or(0, |(_, _, w)| w)
+    }
+
+    /// Draws the rows, returning where each one ended up
+    pub fn render(self, area: Rect, buf: &mut Buffer, list: &List, task_map: &HashMap<Uuid, Task>) -> Vec<RowArea> {
         buf.set_style(area, self.style);
         if let Some(b) = &self.block {
             b.clone().render(area, buf)
@@ -87,37 +145,45 @@ impl TaskListWidget<'_> {
         let widget_area = self.block.as_ref().map_or(area, |b| b.inner(area));
 
         if widget_area.is_empty() {
-            return;
+            return vec![];
         }
 
-        self.render_tasks(widget_area, buf, list, task_map);
+        self.render_tasks(widget_area, buf, list, task_map)
 
     }
 
-    fn render_tasks(&self, area: Rect, buf: &mut Buffer, list: &List, task_map: &HashMap<Uuid, Task>) {
+    fn render_tasks(&self, area: Rect, buf: &mut Buffer, list: &List, task_map: &HashMap<Uuid, Task>) -> Vec<RowArea> {
         let mut y_offset = 0;
+        let mut layout = vec![];
 
-        let columns = vec![TableColumn::State, TableColumn::Description];
-        let widths = get_widths(&self.widths, &columns, area.width);
+        let widths = get_widths(&self.widths, &self.columns, area.width);
 
-        for (i, row) in self.rows.iter().enumerate() {
-            if i < list.focus {
-                continue;
-            }
+        // Only what's on screen, starting where the view is scrolled to
+        for (i, row) in self.rows.iter().enumerate().skip(list.focus) {
             let y_off = super::row::render_row(row, area, buf, super::row::RenderContext {
                 y: y_offset,
                 depth: 0,
-                theme: self.theme.clone(),
+                theme: &self.theme,
                 widths: &widths,
                 list,
                 index: i,
                 task_map,
+                udas: &self.udas,
+            });
+            let fold = row.fold_position(list, task_map, &self.theme, &widths)
+                .map(|(x, y)| (area.x + x, area.y + y_offset + y))
+                .filter(|(_, y)| *y < area.bottom());
+            layout.push(RowArea {
+                index: i,
+                area: Rect::new(area.x, area.y + y_offset, area.width, y_off),
+                fold,
             });
             y_offset += y_off;
             if y_offset >= area.height {
                 break;
             }
         };
+        layout
     }
 
 }
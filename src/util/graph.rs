@@ -1,4 +1,4 @@
-use std::collections::{HashMap, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 
 use uuid::Uuid;
 
@@ -15,10 +15,18 @@ pub struct TextNode {
     pub sub: Vec<Node>
 }
 
+/// The `index`th annotation of the task `val`. Annotations don't have children.
+#[derive(Debug, Clone)]
+pub struct AnnotationNode {
+    pub val: Uuid,
+    pub index: usize,
+}
+
 #[derive(Debug, Clone)]
 pub enum Node {
     Text(TextNode),
     Task(TaskNode),
+    Annotation(AnnotationNode),
 }
 
 impl Node {
@@ -31,10 +39,15 @@ impl Node {
         Node::Text(TextNode { val: Uuid::new_v4(), text, sub: children })
     }
 
+    pub fn annotation(uuid: Uuid, index: usize) -> Node {
+        Node::Annotation(AnnotationNode { val: uuid, index })
+    }
+
     pub fn sub(&self) -> &[Node] {
         match self {
             Node::Text(t) => &t.sub,
             Node::Task(t) => &t.sub,
+            Node::Annotation(_) => &[],
         }
     }
 
@@ -54,6 +67,7 @@ impl Node {
         match self {
             Node::Text(t) => t.sub.push(row),
             Node::Task(t) => t.sub.push(row),
+            Node::Annotation(_) => {},
         }
     }
 
@@ -61,6 +75,7 @@ impl Node {
         match self {
             Node::Text(t) => t.sub,
             Node::Task(t) => t.sub,
+            Node::Annotation(_) => vec![],
         }
     }
 
@@ -76,6 +91,7 @@ impl Idable for Node {
         match self {
             Node::Text(t) => t.val,
             Node::Task(t) => t.val,
+            Node::Annotation(a) => a.val,
         }
     }
 
@@ -83,6 +99,7 @@ impl Idable for Node {
         match self {
             Node::Text(t) => &t.val,
             Node::Task(t) => &t.val,
+            Node::Annotation(a) => &a.val,
         }
     }
 
@@ -103,6 +120,15 @@ pub trait ParentToChild {
 
 }
 
+/// Values that can be blocked by other values. Ids are the keys of the map the values are in.
+pub trait DependsOn {
+
+    fn depends(&self) -> &[Uuid];
+    /// Resolved values don't block anything anymore
+    fn is_resolved(&self) -> bool;
+
+}
+
 
 
 pub fn graph_nodes<T: ParentToChild>(id_map: &HashMap<Uuid, T>) -> Vec<Node> {
@@ -253,6 +279,264 @@ pub fn graph_nodes<T: ParentToChild>(id_map: &HashMap<Uuid, T>) -> Vec<Node> {
 
 
 
+pub fn dependency_nodes<T: DependsOn>(id_map: &HashMap<Uuid, T>) -> Vec<Node> {
+    // Dependencies are a graph, not a tree. A task can block multiple tasks, so
+    // to display blocking chains as a tree blockers get repeated under every task
+    // they block. Only the first one has the blocker's own blockers under it,
+    // later ones are leaves, otherwise diamonds would repeat whole subtrees and
+    // the tree would grow exponentially with them.
+    //
+    // Roots are the tasks nothing depends on, the "goals". Their children are
+    // what blocks them, and so on downwards.
+    //
+    // Like graph_nodes this doesn't recurse. Each frame on the stack is a node
+    // that is still being built along with the children it still has to visit.
+    // When a frame runs out of children it's popped and pushed into its parent.
+    //
+    // Cycles: an edge to a task that's already in the current path is dropped.
+    // Tasks that are only reachable through a cycle have no root, so once all
+    // roots are done any task that hasn't been visited starts a new tree.
+
+    let depended_on: HashSet<Uuid> = id_map.iter()
+        .flat_map(|(uuid, val)| val.depends().iter().filter(move |d| *d != uuid))
+        .filter(|d| id_map.contains_key(d))
+        .copied()
+        .collect();
+
+    let mut starts: Vec<Uuid> = id_map.keys().filter(|u| !depended_on.contains(u)).copied().collect();
+    let mut rest: Vec<Uuid> = id_map.keys().filter(|u| depended_on.contains(u)).copied().collect();
+    // Reverse since they're popped from the back
+    rest.sort_by(|a, b| b.cmp(a));
+
+    let children = |uuid: &Uuid| -> Vec<Uuid> {
+        id_map.get(uuid).expect("task went missing")
+            .depends().iter()
+            .filter(|d| id_map.contains_key(d))
+            .copied()
+            .collect()
+    };
+
+    let mut visited: HashSet<Uuid> = HashSet::new();
+    let mut rows = Vec::new();
+
+    loop {
+        let root = match starts.pop() {
+            Some(r) => r,
+            None => {
+                match rest.iter().rposition(|u| !visited.contains(u)) {
+                    Some(i) => rest.remove(i),
+                    None => break,
+                }
+            }
+        };
+
+        let mut path: HashSet<Uuid> = HashSet::new();
+        let mut stack: Vec<(Uuid, Vec<Uuid>, Node)> = Vec::new();
+        path.insert(root);
+        visited.insert(root);
+        stack.push((root, children(&root), Node::task(root, vec![])));
+
+        while let Some((_, remaining, _)) = stack.last_mut() {
+            if let Some(child) = remaining.pop() {
+                if path.contains(&child) {
+                    // Cycle
+                    continue;
+                }
+                if visited.contains(&child) {
+                    let (_, _, parent) = stack.last_mut().expect("stack went missing");
+                    parent.push(Node::task(child, vec![]));
+                    continue;
+                }
+                path.insert(child);
+                visited.insert(child);
+                stack.push((child, children(&child), Node::task(child, vec![])));
+                continue;
+            }
+            let (uuid, _, node) = stack.pop().expect("stack went missing");
+            path.remove(&uuid);
+            match stack.last_mut() {
+                Some((_, _, parent)) => parent.push(node),
+                None => rows.push(node),
+            }
+        }
+    }
+
+    rows
+}
+
+/// Finds every dependency cycle, each cycle is listed in the order it depends on itself.
+pub fn dependency_cycles<T: DependsOn>(id_map: &HashMap<Uuid, T>) -> Vec<Vec<Uuid>> {
+    // Depth first search, tasks on the current path are "visiting". Reaching a
+    // visiting task again means everything on the path from it is a cycle.
+    let mut visiting: HashSet<Uuid> = HashSet::new();
+    let mut done: HashSet<Uuid> = HashSet::new();
+    let mut cycles = Vec::new();
+
+    let mut starts: Vec<&Uuid> = id_map.keys().collect();
+    starts.sort();
+
+    for start in starts {
+        if done.contains(start) {
+            continue;
+        }
+        // (task, index of the next dependency to check)
+        let mut stack: Vec<(Uuid, usize)> = vec![(*start, 0)];
+        visiting.insert(*start);
+        while let Some((uuid, next)) = stack.last_mut() {
+            let depends = id_map.get(uuid).expect("task went missing").depends();
+            if *next >= depends.len() {
+                visiting.remove(uuid);
+                done.insert(*uuid);
+                stack.pop();
+                continue;
+            }
+            let dependency = depends[*next];
+            *next += 1;
+            if !id_map.contains_key(&dependency) || done.contains(&dependency) {
+                continue;
+            }
+            if visiting.contains(&dependency) {
+                let position = stack.iter().position(|(u, _)| *u == dependency).expect("path went missing");
+                cycles.push(stack[position..].iter().map(|(u, _)| *u).collect());
+                continue;
+            }
+            visiting.insert(dependency);
+            stack.push((dependency, 0));
+        }
+    }
+    cycles
+}
+
+/// Groups unresolved values by when they can be done. The first level has nothing blocking it,
+/// the second only depends on the first, and so on.
+///
+/// Values that are part of, or depend on, a cycle can never be done and are left out.
+pub fn dependency_levels<T: DependsOn>(id_map: &HashMap<Uuid, T>) -> Vec<Vec<Uuid>> {
+    // Kahn's algorithm, but a whole level is taken at once instead of one value at a time
+    let unresolved = |uuid: &Uuid| id_map.get(uuid).is_some_and(|v| !v.is_resolved());
+
+    let mut blocking_count: HashMap<Uuid, usize> = HashMap::new();
+    let mut blocks: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
+    for (uuid, val) in id_map.iter().filter(|(u, _)| unresolved(u)) {
+        let mut depends: Vec<&Uuid> = val.depends().iter().filter(|d| unresolved(d)).collect();
+        depends.sort();
+        depends.dedup();
+        blocking_count.insert(*uuid, depends.len());
+        for dependency in depends {
+            blocks.entry(*dependency).or_default().push(*uuid);
+        }
+    }
+
+    let mut level: Vec<Uuid> = blocking_count.iter().filter(|(_, c)| **c == 0).map(|(u, _)| *u).collect();
+    let mut levels = Vec::new();
+    while !level.is_empty() {
+        let mut next = Vec::new();
+        for uuid in &level {
+            for blocked in blocks.get(uuid).map_or(&[][..], |b| b.as_slice()) {
+                let count = blocking_count.get_mut(blocked).expect("count went missing");
+                *count -= 1;
+                if *count == 0 {
+                    next.push(*blocked);
+                }
+            }
+        }
+        levels.push(level);
+        level = next;
+    }
+    levels
+}
+
+/// The longest chain of unresolved dependencies leading to `goal`. The chain starts with something
+/// that isn't blocked and ends with the goal.
+pub fn critical_path<T: DependsOn>(id_map: &HashMap<Uuid, T>, goal: &Uuid) -> Vec<Uuid> {
+    let unresolved = |uuid: &Uuid| id_map.get(uuid).is_some_and(|v| !v.is_resolved());
+    if !unresolved(goal) {
+        return vec![];
+    }
+
+    // Post order depth first search. Once all of a value's dependencies are done,
+    // its longest chain is one more than the longest of theirs.
+    // Dependencies on the current path are cycles and get skipped.
+    let mut length: HashMap<Uuid, usize> = HashMap::new();
+    let mut next: HashMap<Uuid, Uuid> = HashMap::new();
+    let mut path: HashSet<Uuid> = HashSet::new();
+    let mut stack: Vec<(Uuid, usize)> = vec![(*goal, 0)];
+    path.insert(*goal);
+
+    while let Some((uuid, index)) = stack.last_mut() {
+        let depends = id_map.get(uuid).expect("task went missing").depends();
+        if let Some(dependency) = depends.get(*index) {
+            *index += 1;
+            if unresolved(dependency) && !length.contains_key(dependency) && !path.contains(dependency) {
+                path.insert(*dependency);
+                stack.push((*dependency, 0));
+            }
+            continue;
+        }
+        let uuid = *uuid;
+        let longest = depends.iter()
+            .filter(|d| unresolved(d) && !path.contains(d))
+            .filter_map(|d| length.get(d).map(|l| (*l, *d)))
+            .max();
+        match longest {
+            Some((l, d)) => {
+                length.insert(uuid, l + 1);
+                next.insert(uuid, d);
+            }
+            None => {
+                length.insert(uuid, 1);
+            }
+        }
+        path.remove(&uuid);
+        stack.pop();
+    }
+
+    let mut chain = vec![*goal];
+    while let Some(n) = next.get(chain.last().expect("chain went missing")) {
+        chain.push(*n);
+    }
+    chain.reverse();
+    chain
+}
+
+/// Which values are blocked and which are blocking, only counting unresolved values.
+#[derive(Debug, Clone, Default)]
+pub struct Dependencies {
+    pub blocked: HashSet<Uuid>,
+    pub blocking: HashSet<Uuid>,
+    pub cycles: Vec<Vec<Uuid>>,
+}
+
+impl Dependencies {
+
+    pub fn new<T: DependsOn>(id_map: &HashMap<Uuid, T>) -> Dependencies {
+        let mut blocked = HashSet::new();
+        let mut blocking = HashSet::new();
+        for (uuid, val) in id_map {
+            if val.is_resolved() {
+                continue;
+            }
+            for dependency in val.depends() {
+                if id_map.get(dependency).is_some_and(|d| !d.is_resolved()) {
+                    blocked.insert(*uuid);
+                    blocking.insert(*dependency);
+                }
+            }
+        }
+        Dependencies {
+            blocked,
+            blocking,
+            cycles: dependency_cycles(id_map),
+        }
+    }
+
+    pub fn in_cycle(&self, uuid: &Uuid) -> bool {
+        self.cycles.iter().any(|c| c.contains(uuid))
+    }
+
+}
+
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -260,6 +544,18 @@ mod tests {
     struct TestParent {
         uuid: Uuid,
         sub_of: Option<Uuid>,
+        depends: Vec<Uuid>,
+        resolved: bool,
+    }
+
+    impl DependsOn for TestParent {
+        fn depends(&self) -> &[Uuid] {
+            &self.depends
+        }
+
+        fn is_resolved(&self) -> bool {
+            self.resolved
+        }
     }
 
     impl ParentToChild for TestParent {
@@ -279,7 +575,7 @@ mod tests {
 
     impl TestParent {
         fn new() -> TestParent {
-            TestParent { uuid: Uuid::new_v4(), sub_of: None }
+            TestParent { uuid: Uuid::new_v4(), sub_of: None, depends: vec![], resolved: false }
         }
     }
 
@@ -373,5 +669,162 @@ mod tests {
         let s6 = s4.try_get(&u6).expect("sub went missing");
         assert_eq!(s6.child_len(), 0);
     }
-}
 
+    #[test]
+    fn dependency_chains() {
+        let t1 = TestParent::new();
+        let mut t2 = TestParent::new();
+        let mut t3 = TestParent::new();
+        let mut t4 = TestParent::new();
+        let t5 = TestParent::new();
+
+        let u1 = t1.uuid;
+        let u2 = t2.uuid;
+        let u3 = t3.uuid;
+        let u4 = t4.uuid;
+
+        // t4 -> t3 -> t2 -> t1, t4 -> t1
+        t2.depends = vec![u1];
+        t3.depends = vec![u2];
+        t4.depends = vec![u3, u1];
+        t4.resolved = true;
+
+        let map: HashMap<_, _> = vec![t1, t2, t3, t4, t5]
+            .into_iter()
+            .map(|t| (t.uuid, t))
+            .collect();
+
+        let rows = dependency_nodes(&map);
+        assert_eq!(rows.len(), 2);
+        let s4 = rows.iter().find(|r| r.get_id() == u4).expect("sub went missing");
+        assert_eq!(s4.child_len(), 2);
+        assert_eq!(s4.try_get(&u1).expect("sub went missing").child_len(), 0);
+        let s3 = s4.try_get(&u3).expect("sub went missing");
+        assert!(s3.try_get(&u2).expect("sub went missing").contains(&u1));
+
+        let deps = Dependencies::new(&map);
+        assert!(deps.blocked.contains(&u2));
+        assert!(deps.blocked.contains(&u3));
+        // Resolved tasks aren't blocked
+        assert!(!deps.blocked.contains(&u4));
+        assert!(deps.blocking.contains(&u1));
+        assert!(deps.cycles.is_empty());
+    }
+
+    #[test]
+    fn dependency_diamonds() {
+        // Every task depends on both tasks of the level below, so repeating whole
+        // subtrees would give 2^levels rows for the bottom task
+        let levels = 20;
+        let mut tasks: Vec<TestParent> = (0..levels * 2).map(|_| TestParent::new()).collect();
+        for level in 1..levels {
+            let below = vec![tasks[level * 2 - 2].uuid, tasks[level * 2 - 1].uuid];
+            tasks[level * 2].depends = below.clone();
+            tasks[level * 2 + 1].depends = below;
+        }
+        let bottom = tasks[0].uuid;
+
+        let map: HashMap<_, _> = tasks
+            .into_iter()
+            .map(|t| (t.uuid, t))
+            .collect();
+
+        let rows = dependency_nodes(&map);
+        assert_eq!(rows.len(), 2);
+        let total: usize = rows.iter().map(|r| r.recursive_child_len()).sum();
+        assert!(total < levels * 6, "{} rows", total);
+
+        // The bottom task is under both tasks of the level above, with nothing left out
+        fn count(node: &Node, uuid: &Uuid) -> usize {
+            let own = usize::from(node.get_id() == *uuid);
+            own + node.sub().iter().map(|s| count(s, uuid)).sum::<usize>()
+        }
+        assert_eq!(rows.iter().map(|r| count(r, &bottom)).sum::<usize>(), 2);
+        for uuid in map.keys() {
+            assert!(rows.iter().any(|r| count(r, uuid) > 0));
+        }
+    }
+
+    #[test]
+    fn dependency_cycle() {
+        let mut t1 = TestParent::new();
+        let mut t2 = TestParent::new();
+        let mut t3 = TestParent::new();
+        let mut t4 = TestParent::new();
+
+        let u1 = t1.uuid;
+        let u2 = t2.uuid;
+        let u3 = t3.uuid;
+        let u4 = t4.uuid;
+
+        // t1 -> t2 -> t3 -> t1, t4 -> t1
+        t1.depends = vec![u2];
+        t2.depends = vec![u3];
+        t3.depends = vec![u1];
+        t4.depends = vec![u1];
+
+        let map: HashMap<_, _> = vec![t1, t2, t3, t4]
+            .into_iter()
+            .map(|t| (t.uuid, t))
+            .collect();
+
+        let cycles = dependency_cycles(&map);
+        assert_eq!(cycles.len(), 1);
+        assert_eq!(cycles[0].len(), 3);
+        assert!(!cycles[0].contains(&u4));
+
+        let rows = dependency_nodes(&map);
+        assert_eq!(rows.len(), 1);
+        let s4 = &rows[0];
+        assert_eq!(s4.get_id(), u4);
+        assert_eq!(s4.recursive_child_len(), 4);
+
+        let without_root: HashMap<_, _> = map.into_iter().filter(|(u, _)| *u != u4).collect();
+        // Cycles with nothing depending on them still show up
+        let rows = dependency_nodes(&without_root);
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].recursive_child_len(), 3);
+    }
+
+    #[test]
+    fn dependency_order() {
+        let mut t1 = TestParent::new();
+        let mut t2 = TestParent::new();
+        let mut t3 = TestParent::new();
+        let mut t4 = TestParent::new();
+        let mut t5 = TestParent::new();
+        let mut t6 = TestParent::new();
+
+        let u1 = t1.uuid;
+        let u2 = t2.uuid;
+        let u3 = t3.uuid;
+        let u4 = t4.uuid;
+        let u5 = t5.uuid;
+        let u6 = t6.uuid;
+
+        // t4 -> t3 -> t2 -> t1, t4 -> t5 -> t1, t1 is done
+        t2.depends = vec![u1];
+        t3.depends = vec![u2];
+        t5.depends = vec![u1];
+        t4.depends = vec![u5, u3];
+        // t6 depends on itself
+        t6.depends = vec![u6];
+        t1.resolved = true;
+
+        let map: HashMap<_, _> = vec![t1, t2, t3, t4, t5, t6]
+            .into_iter()
+            .map(|t| (t.uuid, t))
+            .collect();
+
+        let mut levels = dependency_levels(&map);
+        levels.iter_mut().for_each(|l| l.sort());
+        let mut first = vec![u2, u5];
+        first.sort();
+        assert_eq!(levels, vec![first, vec![u3], vec![u4]]);
+
+        assert_eq!(critical_path(&map, &u4), vec![u2, u3, u4]);
+        assert_eq!(critical_path(&map, &u5), vec![u5]);
+        assert_eq!(critical_path(&map, &u1), vec![]);
+        assert_eq!(critical_path(&map, &u6), vec![u6]);
+    }
+}